@@ -0,0 +1,82 @@
+use crate::action::AttributeStatusCollection;
+use crate::{Attribute, AttributeCollection, CharacterId, Roster, Status, StatusCollection};
+use serde::{Deserialize, Serialize};
+
+/// Whose collections a [`Condition`] is evaluated against
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Subject {
+    Actor,
+    #[default]
+    Receiver,
+}
+
+/// A predicate over the attributes and statuses of the actor and the receiver of an action.
+/// Attribute and ratio conditions on attributes the subject does not have are false, so a
+/// [`Condition::Not`] of one of them holds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Condition<A: Attribute, S: Status> {
+    Always,
+    HasStatus(Subject, S),
+    AttributeBelow(Subject, A::Identifier, A::Value),
+    AttributeAbove(Subject, A::Identifier, A::Value),
+    /// The first attribute divided by the second is below the ratio, e.g. HP below 30% of max HP
+    RatioBelow(Subject, A::Identifier, A::Identifier, A::Value),
+    /// The first attribute divided by the second is above the ratio
+    RatioAbove(Subject, A::Identifier, A::Identifier, A::Value),
+    Not(Box<Condition<A, S>>),
+    All(Vec<Condition<A, S>>),
+    Any(Vec<Condition<A, S>>),
+}
+
+impl<A: Attribute, S: Status> Condition<A, S> {
+    pub fn evaluate(
+        &self,
+        actor: &AttributeStatusCollection<A, S>,
+        receiver: &AttributeStatusCollection<A, S>,
+    ) -> bool {
+        let subject = |s: &Subject| match s {
+            Subject::Actor => actor,
+            Subject::Receiver => receiver,
+        };
+        let ratio = |s: &Subject, i: &A::Identifier, of: &A::Identifier| {
+            let (attributes, _) = subject(s);
+            Some(attributes.get_attribute_value(i)? / attributes.get_attribute_value(of)?)
+        };
+        match self {
+            Self::Always => true,
+            Self::HasStatus(s, status) => subject(s).1.contains(status),
+            Self::AttributeBelow(s, i, v) => {
+                subject(s).0.get_attribute_value(i).is_some_and(|x| x < *v)
+            }
+            Self::AttributeAbove(s, i, v) => {
+                subject(s).0.get_attribute_value(i).is_some_and(|x| x > *v)
+            }
+            Self::RatioBelow(s, i, of, r) => ratio(s, i, of).is_some_and(|x| x < *r),
+            Self::RatioAbove(s, i, of, r) => ratio(s, i, of).is_some_and(|x| x > *r),
+            Self::Not(c) => !c.evaluate(actor, receiver),
+            Self::All(v) => v.iter().all(|c| c.evaluate(actor, receiver)),
+            Self::Any(v) => v.iter().any(|c| c.evaluate(actor, receiver)),
+        }
+    }
+
+    /// Evaluates the condition for the characters with the given ids. It never holds if either
+    /// of them is missing from the rosters.
+    pub(crate) fn evaluate_in(
+        &self,
+        attribute_collections: &Roster<&mut AttributeCollection<A>>,
+        status_collections: &Roster<&mut StatusCollection<S>>,
+        actor: CharacterId,
+        receiver: CharacterId,
+    ) -> bool {
+        let collections = |id| {
+            Some((
+                &**attribute_collections.get(id)?,
+                &**status_collections.get(id)?,
+            ))
+        };
+        match (collections(actor), collections(receiver)) {
+            (Some(actor), Some(receiver)) => self.evaluate(&actor, &receiver),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{Attribute, CharacterId, Status, Target, ValidationError};
+use thiserror::Error;
+
+/// Why an action could not be executed
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ActionError<A: Attribute, S: Status> {
+    #[error("{0:?} is not part of the encounter")]
+    MissingCharacter(CharacterId),
+    #[error("the actor has no {0:?} attribute")]
+    MissingAttribute(A::Identifier),
+    #[error("{character:?} has no {identifier:?} attribute")]
+    MissingReceiverAttribute {
+        character: CharacterId,
+        identifier: A::Identifier,
+    },
+    #[error("{0:?} was not resolved to any characters")]
+    UnresolvedTarget(Target<A::Identifier>),
+    /// Only reported in strict mode, see [`Encounter::set_strict`](crate::Encounter::set_strict)
+    #[error("the action is invalid: {0:?}")]
+    Invalid(Vec<ValidationError<A>>),
+    #[error("custom actions cannot be applied")]
+    CustomAction,
+    #[error("the actor is kept from using the action by {0:?}")]
+    Blocked(S),
+    #[error("{action} is usable again in {turns} turns")]
+    OnCooldown { action: String, turns: u32 },
+    #[error("not enough {identifier:?}: {required:?} required, {available:?} available")]
+    InsufficientAttribute {
+        identifier: A::Identifier,
+        required: A::Value,
+        available: A::Value,
+    },
+}
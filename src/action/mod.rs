@@ -1,23 +1,144 @@
+pub mod condition;
+pub mod error;
 pub mod modifier;
 pub mod output;
+pub mod result;
+pub mod validate;
 
-use crate::{Attribute, AttributeCollection, AttributeValue, Status, StatusCollection};
+use crate::{
+    Attribute, AttributeCollection, AttributeIdentifier, AttributeValue, Character, CharacterId,
+    Cooldown, Rng, Roster, Status, StatusCollection,
+};
+pub use condition::{Condition, Subject};
+pub use error::ActionError;
 pub use modifier::Modifier;
 use modifier::{IncomingModifierCollection, OutgoingModifierCollection};
+pub use result::{ActionResult, CharacterResult};
 use serde::{Deserialize, Serialize};
 use std::any::Any;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Debug;
+pub use validate::{validate, ValidationError};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "InnerAction<A, S>: Serialize, AttributeChange<A>: Serialize",
+    deserialize = "InnerAction<A, S>: Deserialize<'de>, AttributeChange<A>: Deserialize<'de>"
+))]
 pub struct Action<A: Attribute, S: Status> {
     name: String,
     inner: InnerAction<A, S>,
+    tags: BTreeSet<String>,
+    costs: Vec<AttributeChange<A>>,
+    cooldown: Option<Cooldown>,
 }
 
 impl<A: Attribute, S: Status> Action<A, S> {
     pub fn new(name: String, inner: InnerAction<A, S>) -> Self {
-        Self { name, inner }
+        Self {
+            name,
+            inner,
+            tags: BTreeSet::new(),
+            costs: vec![],
+            cooldown: None,
+        }
+    }
+
+    /// Adds a category such as "spell" or "movement", see [`Status::blocks`]
+    pub fn with_tag<T: ToString>(mut self, tag: T) -> Self {
+        self.tags.insert(tag.to_string());
+        self
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Adds a change the actor has to pay for before the action is executed, e.g. a negative
+    /// `Add` on mana. Costs are never routed through layers such as barriers.
+    pub fn with_cost(mut self, cost: AttributeChange<A>) -> Self {
+        self.costs.push(cost);
+        self
+    }
+
+    pub fn costs(&self) -> &[AttributeChange<A>] {
+        &self.costs
+    }
+
+    /// Limits how often a character can use the action, see [`Cooldowns`](crate::Cooldowns)
+    pub fn with_cooldown(mut self, cooldown: Cooldown) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    pub fn cooldown(&self) -> Option<Cooldown> {
+        self.cooldown
+    }
+
+    /// The costs after the actor's outgoing cost modifiers, e.g. mana cost reductions, or why
+    /// the actor cannot pay them. Only modifiers added with
+    /// [`add_cost_by_action_name`](modifier::ModifierCollection::add_cost_by_action_name) or
+    /// [`add_cost_by_action_tag`](modifier::ModifierCollection::add_cost_by_action_tag) apply.
+    /// Negative `Add` costs may not take an attribute below zero.
+    pub fn check_costs<M>(
+        &self,
+        actor: &Actor<A, S, M>,
+    ) -> Result<Vec<AttributeChange<A>>, ActionError<A, S>>
+    where
+        M: Modifier<Attr = A>,
+    {
+        let (attributes, _, outgoing) = actor;
+        let costs: Vec<_> = self
+            .costs
+            .iter()
+            .map(|c| outgoing.generate_cost(c, self))
+            .collect();
+
+        let zero = A::Value::default();
+        let mut available = HashMap::new();
+        for cost in &costs {
+            let identifier = cost.identifier();
+            let value = match available.get(identifier) {
+                Some(value) => *value,
+                None => attributes
+                    .get_attribute_value(identifier)
+                    .ok_or_else(|| ActionError::MissingAttribute(identifier.clone()))?,
+            };
+            let required = zero - cost.change();
+            if cost.op == AttributeChangeType::Add && required > zero {
+                if value < required {
+                    return Err(ActionError::InsufficientAttribute {
+                        identifier: identifier.clone(),
+                        required,
+                        available: value,
+                    });
+                }
+                available.insert(identifier.clone(), value - required);
+            }
+        }
+        Ok(costs)
+    }
+
+    /// Pays every cost at once if the actor can afford all of them, and nothing otherwise
+    pub fn pay_costs<M>(
+        &self,
+        attributes: &mut AttributeCollection<A>,
+        statuses: &StatusCollection<S>,
+        outgoing: &OutgoingModifierCollection<M>,
+    ) -> Result<Vec<AttributeDelta<A>>, ActionError<A, S>>
+    where
+        M: Modifier<Attr = A>,
+    {
+        let costs = self.check_costs(&(&*attributes, statuses, outgoing))?;
+        Ok(costs
+            .iter()
+            .filter_map(|c| c.apply_unrouted(attributes, c.change))
+            .collect())
     }
     pub fn name(&self) -> &str {
         self.name.as_str()
@@ -39,11 +160,56 @@ impl<A: Attribute, S: Status> Action<A, S> {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Whether `character` can use `action` right now, or the reason it cannot: a status blocking
+/// the action, a cooldown, or costs it cannot pay
+pub fn can_use<A, S, M>(
+    action: &Action<A, S>,
+    character: &Character<A, S, M>,
+) -> Result<(), ActionError<A, S>>
+where
+    A: Attribute,
+    S: Status,
+    M: Modifier<Attr = A>,
+{
+    let (base, attributes, statuses, _, outgoing) = character;
+    if let Some(status) = statuses.iter().find(|s| s.blocks(action.tags())) {
+        return Err(ActionError::Blocked(status.clone()));
+    }
+    let turns = base.cooldowns().turns_until_ready(action.name());
+    if turns > 0 {
+        return Err(ActionError::OnCooldown {
+            action: action.name().to_string(),
+            turns,
+        });
+    }
+    action.check_costs(&(attributes, statuses, outgoing))?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "SimpleAction<A, S>: Serialize, Condition<A, S>: Serialize",
+    deserialize = "SimpleAction<A, S>: Deserialize<'de>, Condition<A, S>: Deserialize<'de>"
+))]
 pub enum InnerAction<A: Attribute, S: Status> {
     Simple(SimpleAction<A, S>),
     SelfOther(SimpleAction<A, S>, SimpleAction<A, S>),
+    /// Cannot be serialized
+    #[serde(skip)]
     Custom(Box<dyn CustomAction>),
+    /// Applies every action in order
+    Sequence(Vec<InnerAction<A, S>>),
+    /// Applies the action the given number of times, e.g. for multi-hit attacks
+    Repeat(u32, Box<InnerAction<A, S>>),
+    /// Applies one of the actions, picked with a probability proportional to its weight
+    RandomChoice(Vec<(u32, InnerAction<A, S>)>),
+    /// Applies the first action if the condition holds and the second otherwise. The
+    /// receiver of the condition is the character in the [`Target::Target`] slot.
+    Conditional(
+        Condition<A, S>,
+        Box<InnerAction<A, S>>,
+        Box<InnerAction<A, S>>,
+    ),
 }
 
 impl<A: Attribute, S: Status> InnerAction<A, S> {
@@ -56,96 +222,415 @@ impl<A: Attribute, S: Status> InnerAction<A, S> {
     where
         M: Modifier<Attr = A>,
     {
+        let recurse = |a: &Self| a.apply_modifiers(actor, receiver, action);
         match self {
             Self::Simple(a) => Self::Simple(a.apply_modifiers(actor, receiver, action)),
             Self::SelfOther(a1, a2) => Self::SelfOther(
                 a1.apply_modifiers(actor, actor, action),
                 a2.apply_modifiers(actor, receiver, action),
             ),
-            _ => todo!(),
+            Self::Sequence(v) => Self::Sequence(v.iter().map(recurse).collect()),
+            Self::Repeat(n, a) => Self::Repeat(*n, Box::new(recurse(a))),
+            Self::RandomChoice(v) => {
+                Self::RandomChoice(v.iter().map(|(w, a)| (*w, recurse(a))).collect())
+            }
+            Self::Conditional(c, a1, a2) => {
+                Self::Conditional(c.clone(), Box::new(recurse(a1)), Box::new(recurse(a2)))
+            }
+            Self::Custom(a) => Self::Custom(a.clone()),
         }
     }
 
+    /// Like [`InnerAction::apply`], but only applies the parts of the action that target the
+    /// actor
     pub fn apply_actor_only(
         &self,
-        attribute_collections: &mut [&mut AttributeCollection<A>],
-        status_collections: &mut [&mut StatusCollection<S>],
-        targets: &HashMap<Target, usize>,
-    ) {
-        match self {
-            InnerAction::Simple(a) | InnerAction::SelfOther(a,_) => {
-                if a.target == Target::Actor {
-                    a.apply(
-                        *attribute_collections
-                            .get_mut(targets[&a.target])
-                            .unwrap(),
-                        *status_collections
-                            .get_mut(targets[&a.target])
-                            .unwrap(),
-                    );
-                }
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        targets: &ResolvedTargets<A::Identifier>,
+        rng: &mut Rng,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>> {
+        self.commit(
+            attribute_collections,
+            status_collections,
+            targets,
+            rng,
+            Mode {
+                actor_only: true,
+                strict: false,
+            },
+            &|a, _, _, _, _| Cow::Borrowed(a),
+        )
+    }
+
+    /// Applies every [`SimpleAction`] to each character resolved for its target and returns the
+    /// realized deltas, tagged with the id of the character they happened on. Changes to
+    /// attributes a receiver does not have are skipped. The action is applied as a whole or not
+    /// at all: if any part fails, e.g. because a target was not resolved, everything applied so
+    /// far is rolled back.
+    pub fn apply(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        targets: &ResolvedTargets<A::Identifier>,
+        rng: &mut Rng,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>> {
+        self.commit(
+            attribute_collections,
+            status_collections,
+            targets,
+            rng,
+            Mode::default(),
+            &|a, _, _, _, _| Cow::Borrowed(a),
+        )
+    }
+
+    /// Like [`InnerAction::apply`], but a change to an attribute a receiver does not have fails
+    /// with [`ActionError::MissingReceiverAttribute`] and rolls the action back
+    pub fn apply_strict(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        targets: &ResolvedTargets<A::Identifier>,
+        rng: &mut Rng,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>> {
+        self.commit(
+            attribute_collections,
+            status_collections,
+            targets,
+            rng,
+            Mode {
+                actor_only: false,
+                strict: true,
+            },
+            &|a, _, _, _, _| Cow::Borrowed(a),
+        )
+    }
+
+    /// Like [`InnerAction::apply`], or [`InnerAction::apply_strict`] if `strict` is set, but
+    /// `prepare` may rewrite each [`SimpleAction`] right before it is applied to a given
+    /// receiver, e.g. to apply that receiver's modifiers
+    pub(crate) fn apply_prepared<F>(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        targets: &ResolvedTargets<A::Identifier>,
+        rng: &mut Rng,
+        strict: bool,
+        prepare: &F,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>>
+    where
+        F: for<'a> Fn(
+            &'a SimpleAction<A, S>,
+            CharacterId,
+            CharacterId,
+            &Roster<&mut AttributeCollection<A>>,
+            &Roster<&mut StatusCollection<S>>,
+        ) -> Cow<'a, SimpleAction<A, S>>,
+    {
+        self.commit(
+            attribute_collections,
+            status_collections,
+            targets,
+            rng,
+            Mode {
+                actor_only: false,
+                strict,
+            },
+            prepare,
+        )
+    }
+
+    /// Walks the action and reverts whatever it applied if it fails part way
+    fn commit<F>(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        targets: &ResolvedTargets<A::Identifier>,
+        rng: &mut Rng,
+        mode: Mode,
+        prepare: &F,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>>
+    where
+        F: for<'a> Fn(
+            &'a SimpleAction<A, S>,
+            CharacterId,
+            CharacterId,
+            &Roster<&mut AttributeCollection<A>>,
+            &Roster<&mut StatusCollection<S>>,
+        ) -> Cow<'a, SimpleAction<A, S>>,
+    {
+        let mut result = ActionResult::new();
+        match self.walk(
+            attribute_collections,
+            status_collections,
+            targets,
+            rng,
+            mode,
+            prepare,
+            &mut result,
+        ) {
+            Ok(()) => Ok(result),
+            Err(e) => {
+                result.revert(attribute_collections, status_collections);
+                Err(e)
             }
-            _ => { todo!() }
         }
     }
 
+    /// Whether applying the action twice to the same state may have different effects
+    pub fn is_random(&self) -> bool {
+        let random_target = self
+            .targets()
+            .iter()
+            .any(|t| matches!(t, Target::RandomEnemy(_)));
+        random_target || self.has_random_choice()
+    }
 
-    pub fn apply(
+    fn has_random_choice(&self) -> bool {
+        match self {
+            InnerAction::RandomChoice(_) => true,
+            InnerAction::Sequence(v) => v.iter().any(|a| a.has_random_choice()),
+            InnerAction::Repeat(_, a) => a.has_random_choice(),
+            InnerAction::Conditional(_, a1, a2) => a1.has_random_choice() || a2.has_random_choice(),
+            InnerAction::Simple(_) | InnerAction::SelfOther(..) | InnerAction::Custom(_) => false,
+        }
+    }
+
+    /// Every target referenced anywhere in the action, in order of first appearance
+    pub fn targets(&self) -> Vec<Target<A::Identifier>> {
+        let mut result = vec![];
+        self.collect_targets(&mut result);
+        result
+    }
+
+    fn collect_targets(&self, result: &mut Vec<Target<A::Identifier>>) {
+        let mut push = |t: &Target<A::Identifier>| {
+            if !result.contains(t) {
+                result.push(t.clone());
+            }
+        };
+        match self {
+            InnerAction::Simple(a) => a.targets().for_each(&mut push),
+            InnerAction::SelfOther(a1, a2) => {
+                push(&Target::Actor);
+                a1.targets().chain(a2.targets()).for_each(&mut push);
+            }
+            InnerAction::Custom(_) => {}
+            InnerAction::Sequence(v) => v.iter().for_each(|a| a.collect_targets(result)),
+            InnerAction::Repeat(_, a) => a.collect_targets(result),
+            InnerAction::RandomChoice(v) => v.iter().for_each(|(_, a)| a.collect_targets(result)),
+            InnerAction::Conditional(_, a1, a2) => {
+                push(&Target::Actor);
+                push(&Target::Target);
+                a1.collect_targets(result);
+                a2.collect_targets(result);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn walk<F>(
         &self,
-        attribute_collections: &mut [&mut AttributeCollection<A>],
-        status_collections: &mut [&mut StatusCollection<S>],
-        targets: &HashMap<Target, usize>,
-    ) {
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        targets: &ResolvedTargets<A::Identifier>,
+        rng: &mut Rng,
+        mode: Mode,
+        prepare: &F,
+        result: &mut ActionResult<A, S>,
+    ) -> Result<(), ActionError<A, S>>
+    where
+        F: for<'a> Fn(
+            &'a SimpleAction<A, S>,
+            CharacterId,
+            CharacterId,
+            &Roster<&mut AttributeCollection<A>>,
+            &Roster<&mut StatusCollection<S>>,
+        ) -> Cow<'a, SimpleAction<A, S>>,
+    {
         match self {
             InnerAction::Simple(a) => {
-                a.apply(
-                    *attribute_collections
-                        .get_mut(targets[&a.target])
-                        .unwrap(),
-                    *status_collections
-                        .get_mut(targets[&a.target])
-                        .unwrap(),
-                );
+                if !mode.actor_only || a.target == Target::Actor {
+                    for receiver in resolve(targets, &a.target)? {
+                        a.apply_with_follow_ups(
+                            attribute_collections,
+                            status_collections,
+                            *receiver,
+                            targets,
+                            mode.strict,
+                            prepare,
+                            result,
+                        )?;
+                    }
+                }
             }
             InnerAction::SelfOther(a1, a2) => {
-                a1.apply(
-                    *attribute_collections
-                        .get_mut(targets[&Target::Actor])
-                        .unwrap(),
-                    *status_collections.get_mut(targets[&Target::Actor]).unwrap(),
-                );
-                a2.apply(
-                    *attribute_collections
-                        .get_mut(targets[&a2.target])
-                        .unwrap(),
-                    *status_collections
-                        .get_mut(targets[&a2.target])
-                        .unwrap(),
-                );
+                for receiver in resolve(targets, &Target::Actor)? {
+                    a1.apply_with_follow_ups(
+                        attribute_collections,
+                        status_collections,
+                        *receiver,
+                        targets,
+                        mode.strict,
+                        prepare,
+                        result,
+                    )?;
+                }
+                if !mode.actor_only || a2.target == Target::Actor {
+                    for receiver in resolve(targets, &a2.target)? {
+                        a2.apply_with_follow_ups(
+                            attribute_collections,
+                            status_collections,
+                            *receiver,
+                            targets,
+                            mode.strict,
+                            prepare,
+                            result,
+                        )?;
+                    }
+                }
+            }
+            InnerAction::Custom(_) => return Err(ActionError::CustomAction),
+            InnerAction::Sequence(v) => {
+                for a in v {
+                    a.walk(
+                        attribute_collections,
+                        status_collections,
+                        targets,
+                        rng,
+                        mode,
+                        prepare,
+                        result,
+                    )?;
+                }
+            }
+            InnerAction::Repeat(n, a) => {
+                for _ in 0..*n {
+                    a.walk(
+                        attribute_collections,
+                        status_collections,
+                        targets,
+                        rng,
+                        mode,
+                        prepare,
+                        result,
+                    )?;
+                }
+            }
+            InnerAction::RandomChoice(v) => {
+                let weights: Vec<u64> = v.iter().map(|(w, _)| *w as u64).collect();
+                if let Some((_, a)) = rng.choose(&weights).map(|i| &v[i]) {
+                    a.walk(
+                        attribute_collections,
+                        status_collections,
+                        targets,
+                        rng,
+                        mode,
+                        prepare,
+                        result,
+                    )?;
+                }
+            }
+            InnerAction::Conditional(c, a1, a2) => {
+                let actor = first(targets, &Target::Actor);
+                let receiver = first(targets, &Target::Target).or(actor);
+                let holds = match (actor, receiver) {
+                    (Some(actor), Some(receiver)) => {
+                        c.evaluate_in(attribute_collections, status_collections, actor, receiver)
+                    }
+                    _ => false,
+                };
+                let a = if holds { a1 } else { a2 };
+                a.walk(
+                    attribute_collections,
+                    status_collections,
+                    targets,
+                    rng,
+                    mode,
+                    prepare,
+                    result,
+                )?;
             }
-            InnerAction::Custom(_) => todo!(),
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone)]
+/// The characters each [`Target`] of an action was resolved to
+pub type ResolvedTargets<I> = HashMap<Target<I>, Vec<CharacterId>>;
+
+/// How an action is walked, see [`InnerAction::apply_actor_only`] and
+/// [`InnerAction::apply_strict`]
+#[derive(Debug, Default, Clone, Copy)]
+struct Mode {
+    actor_only: bool,
+    strict: bool,
+}
+
+/// The characters `target` was resolved to, or an error if it was not resolved at all
+fn resolve<'a, A: Attribute, S: Status>(
+    targets: &'a ResolvedTargets<A::Identifier>,
+    target: &Target<A::Identifier>,
+) -> Result<&'a [CharacterId], ActionError<A, S>> {
+    targets
+        .get(target)
+        .map(|v| v.as_slice())
+        .ok_or_else(|| ActionError::UnresolvedTarget(target.clone()))
+}
+
+fn first<I: AttributeIdentifier>(
+    targets: &ResolvedTargets<I>,
+    target: &Target<I>,
+) -> Option<CharacterId> {
+    targets.get(target).and_then(|v| v.first()).copied()
+}
+
+/// Realized deltas of an application, paired with the id of the affected character
+pub type CharacterDeltas<A, S> = Vec<(CharacterId, Delta<A, S>)>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "ActionElement<A, S>: Serialize, FollowUp<A>: Serialize, A::Identifier: Serialize",
+    deserialize = "ActionElement<A, S>: Deserialize<'de>, FollowUp<A>: Deserialize<'de>, \
+                   A::Identifier: Deserialize<'de>"
+))]
 pub struct SimpleAction<A: Attribute, S: Status> {
-    target: Target,
+    target: Target<A::Identifier>,
     elements: Vec<ActionElement<A, S>>,
+    follow_ups: Vec<FollowUp<A>>,
 }
 
 impl<A: Attribute, S: Status> SimpleAction<A, S> {
-    pub fn new_empty(target: Target) -> Self {
+    pub fn new_empty(target: Target<A::Identifier>) -> Self {
         Self::new(target, vec![])
     }
-    pub fn new(target: Target, elements: Vec<ActionElement<A, S>>) -> Self {
-        Self { target, elements }
+    pub fn new(target: Target<A::Identifier>, elements: Vec<ActionElement<A, S>>) -> Self {
+        Self {
+            target,
+            elements,
+            follow_ups: vec![],
+        }
     }
 
-    pub fn set_target(&mut self, target: Target) {
+    pub fn with_follow_up(mut self, follow_up: FollowUp<A>) -> Self {
+        self.follow_ups.push(follow_up);
+        self
+    }
+
+    pub fn set_target(&mut self, target: Target<A::Identifier>) {
         self.target = target;
     }
 
+    pub fn target(&self) -> &Target<A::Identifier> {
+        &self.target
+    }
+
+    /// The target of the action itself followed by those of its follow-ups
+    pub fn targets(&self) -> impl Iterator<Item = &Target<A::Identifier>> {
+        std::iter::once(&self.target).chain(self.follow_ups.iter().map(|f| &f.target))
+    }
+
     pub fn apply_modifiers<M>(
         &self,
         actor: &Actor<A, S, M>,
@@ -155,26 +640,209 @@ impl<A: Attribute, S: Status> SimpleAction<A, S> {
     where
         M: Modifier<Attr = A>,
     {
-        let mut result = SimpleAction::new_empty(self.target);
-        for e in &self.elements {
-            match e {
-                ActionElement::AttributeChange(a) => result.elements.push(
-                    ActionElement::AttributeChange(a.apply_modifiers(actor, receiver, action)),
-                ),
-                other => result.elements.push(other.clone()),
-            }
-        }
+        let mut result = SimpleAction::new_empty(self.target.clone());
+        result.follow_ups = self.follow_ups.clone();
+        result.elements = self
+            .elements
+            .iter()
+            .map(|e| e.apply_modifiers(actor, receiver, action))
+            .collect();
         result
     }
 
+    /// Applies every element to the character at `receiver`. Conditions are evaluated against
+    /// the actor and the receiver right before their element would be applied. If an element
+    /// fails, the elements applied before it are rolled back.
     pub fn apply(
         &self,
-        attributes: &mut AttributeCollection<A>,
-        statuses: &mut StatusCollection<S>,
-    ) {
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        actor: CharacterId,
+        receiver: CharacterId,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>> {
+        let mut result = ActionResult::new();
+        match self.apply_elements(
+            attribute_collections,
+            status_collections,
+            actor,
+            receiver,
+            false,
+            &mut result,
+        ) {
+            Ok(()) => Ok(result),
+            Err(e) => {
+                result.revert(attribute_collections, status_collections);
+                Err(e)
+            }
+        }
+    }
+
+    fn apply_elements(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        actor: CharacterId,
+        receiver: CharacterId,
+        strict: bool,
+        result: &mut ActionResult<A, S>,
+    ) -> Result<(), ActionError<A, S>> {
         for e in &self.elements {
-            e.apply(attributes, statuses);
+            e.apply(
+                attribute_collections,
+                status_collections,
+                actor,
+                receiver,
+                strict,
+                result,
+            )?;
         }
+        Ok(())
+    }
+
+    /// Applies the action to the character at `receiver`, then derives and applies its
+    /// follow-ups from what was actually dealt, and the incoming follow-ups of the receiver from
+    /// what it actually received. Receivers that are no longer present are skipped.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_with_follow_ups<F>(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        receiver: CharacterId,
+        targets: &ResolvedTargets<A::Identifier>,
+        strict: bool,
+        prepare: &F,
+        result: &mut ActionResult<A, S>,
+    ) -> Result<(), ActionError<A, S>>
+    where
+        F: for<'a> Fn(
+            &'a SimpleAction<A, S>,
+            CharacterId,
+            CharacterId,
+            &Roster<&mut AttributeCollection<A>>,
+            &Roster<&mut StatusCollection<S>>,
+        ) -> Cow<'a, SimpleAction<A, S>>,
+    {
+        let actor = first(targets, &Target::Actor).unwrap_or(receiver);
+        if !attribute_collections.contains(actor) || !attribute_collections.contains(receiver) {
+            return Ok(());
+        }
+        let prepared = prepare(
+            self,
+            actor,
+            receiver,
+            attribute_collections,
+            status_collections,
+        );
+        let start = result.len();
+        prepared.apply_elements(
+            attribute_collections,
+            status_collections,
+            actor,
+            receiver,
+            strict,
+            result,
+        )?;
+        let realized = &result.deltas()[start..];
+        let follow_ups: Vec<_> = self
+            .follow_ups
+            .iter()
+            .filter_map(|f| f.generate(realized))
+            .collect();
+        let received: Vec<_> = realized
+            .iter()
+            .filter(|(c, _)| *c == receiver)
+            .cloned()
+            .collect();
+        let incoming: Vec<_> = attribute_collections[receiver]
+            .incoming_follow_ups()
+            .iter()
+            .filter_map(|f| f.generate(&received))
+            .collect();
+        for a in follow_ups {
+            for idx in resolve(targets, &a.target)? {
+                a.apply_elements(
+                    attribute_collections,
+                    status_collections,
+                    actor,
+                    *idx,
+                    strict,
+                    result,
+                )?;
+            }
+        }
+        // The receiver is the source of its own follow-ups, which do not trigger any others
+        for a in incoming {
+            for idx in targets.get(&a.target).into_iter().flatten() {
+                a.apply_elements(
+                    attribute_collections,
+                    status_collections,
+                    receiver,
+                    *idx,
+                    strict,
+                    result,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An extra change computed from the realized deltas of a [`SimpleAction`] on its receiver,
+/// e.g. lifesteal or reflect. The follow-up changes `identifier` on `target` by `ratio` times
+/// the sum of the realized deltas of `watch`. Follow-ups of an action belong to the actor, see
+/// [`SimpleAction::with_follow_up`], while incoming follow-ups belong to the receiver, see
+/// [`AttributeCollection::add_incoming_follow_up`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FollowUp<A: Attribute> {
+    watch: A::Identifier,
+    ratio: A::Value,
+    target: Target<A::Identifier>,
+    identifier: A::Identifier,
+}
+
+impl<A: Attribute> FollowUp<A> {
+    pub fn new(
+        watch: A::Identifier,
+        ratio: A::Value,
+        target: Target<A::Identifier>,
+        identifier: A::Identifier,
+    ) -> Self {
+        Self {
+            watch,
+            ratio,
+            target,
+            identifier,
+        }
+    }
+
+    /// Heals the actor for `ratio` of the damage actually dealt to `identifier`
+    pub fn lifesteal(identifier: A::Identifier, ratio: A::Value) -> Self {
+        let zero = A::Value::default();
+        Self::new(identifier.clone(), zero - ratio, Target::Actor, identifier)
+    }
+
+    /// Deals `ratio` of the damage actually received on `identifier` back to the actor. Meant to
+    /// be added to the receiver with [`AttributeCollection::add_incoming_follow_up`].
+    pub fn reflect(identifier: A::Identifier, ratio: A::Value) -> Self {
+        Self::new(identifier.clone(), ratio, Target::Actor, identifier)
+    }
+
+    pub(crate) fn generate<S: Status>(
+        &self,
+        realized: &[(CharacterId, Delta<A, S>)],
+    ) -> Option<SimpleAction<A, S>> {
+        let total = realized
+            .iter()
+            .filter_map(|(_, d)| match d {
+                Delta::Attribute(a) if a.identifier() == &self.watch => Some(a),
+                _ => None,
+            })
+            .map(|d| d.delta())
+            .reduce(|a, b| a + b)?;
+        Some(SimpleAction::new(
+            self.target.clone(),
+            vec![AttributeChange::new(self.identifier.clone(), total * self.ratio).into()],
+        ))
     }
 }
 
@@ -220,29 +888,112 @@ impl Clone for Box<dyn CustomAction> {
 // Target & Changes
 // ===============
 
-#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize, PartialOrd, PartialEq, Eq, Hash)]
-pub enum Target {
+/// Who a [`SimpleAction`] is applied to. Apart from `Actor` and `Target`, the chosen target,
+/// targets are resolved against the roster of an [`Encounter`](crate::Encounter).
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialOrd, PartialEq, Eq, Hash)]
+pub enum Target<I: AttributeIdentifier> {
     Actor,
     #[default]
     Target,
+    AllEnemies,
+    /// Every character not hostile to the actor, including the actor
+    AllAllies,
+    Everyone,
+    /// Up to the given number of distinct enemies, picked at random
+    RandomEnemy(u32),
+    /// The enemy with the lowest value of the attribute
+    LowestAttribute(I),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "AttributeChange<A>: Serialize, S: Serialize, Condition<A, S>: Serialize",
+    deserialize = "AttributeChange<A>: Deserialize<'de>, S: Deserialize<'de>, \
+                   Condition<A, S>: Deserialize<'de>"
+))]
 pub enum ActionElement<A: Attribute, S: Status> {
     AttributeChange(AttributeChange<A>),
     StatusChange(StatusChange<S>),
+    /// Elements only applied if the condition holds
+    Conditional(Condition<A, S>, Vec<ActionElement<A, S>>),
+    Summon(Summon),
 }
 
 impl<A: Attribute, S: Status> ActionElement<A, S> {
+    pub(crate) fn apply_modifiers<M>(
+        &self,
+        actor: &Actor<A, S, M>,
+        receiver: &Receiver<A, S, M>,
+        action: &Action<A, S>,
+    ) -> Self
+    where
+        M: Modifier<Attr = A>,
+    {
+        match self {
+            ActionElement::AttributeChange(a) => {
+                ActionElement::AttributeChange(a.apply_modifiers(actor, receiver, action))
+            }
+            ActionElement::Conditional(c, v) => ActionElement::Conditional(
+                c.clone(),
+                v.iter()
+                    .map(|e| e.apply_modifiers(actor, receiver, action))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// Applies the element to `receiver`, pushing every realized delta to `result`. Attribute
+    /// changes on attributes the receiver does not have are skipped, or fail if `strict` is set.
     pub(crate) fn apply(
         &self,
-        attributes: &mut AttributeCollection<A>,
-        statuses: &mut StatusCollection<S>,
-    ) {
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+        actor: CharacterId,
+        receiver: CharacterId,
+        strict: bool,
+        result: &mut ActionResult<A, S>,
+    ) -> Result<(), ActionError<A, S>> {
         match self {
-            ActionElement::AttributeChange(a) => a.apply(attributes),
-            ActionElement::StatusChange(s) => s.apply(statuses),
+            ActionElement::AttributeChange(a) => {
+                let attributes = attribute_collections
+                    .get_mut(receiver)
+                    .ok_or(ActionError::MissingCharacter(receiver))?;
+                if strict && attributes.get_attribute(a.identifier()).is_none() {
+                    return Err(ActionError::MissingReceiverAttribute {
+                        character: receiver,
+                        identifier: a.identifier().clone(),
+                    });
+                }
+                for delta in a.apply(attributes) {
+                    result.push(receiver, Delta::Attribute(delta));
+                }
+            }
+            ActionElement::StatusChange(s) => {
+                let statuses = status_collections
+                    .get_mut(receiver)
+                    .ok_or(ActionError::MissingCharacter(receiver))?;
+                if let Some(delta) = s.apply(statuses) {
+                    result.push(receiver, delta);
+                }
+            }
+            ActionElement::Summon(s) => result.push(receiver, Delta::Summon(s.clone())),
+            ActionElement::Conditional(c, v) => {
+                if c.evaluate_in(attribute_collections, status_collections, actor, receiver) {
+                    for e in v {
+                        e.apply(
+                            attribute_collections,
+                            status_collections,
+                            actor,
+                            receiver,
+                            strict,
+                            result,
+                        )?;
+                    }
+                }
+            }
         }
+        Ok(())
     }
 }
 
@@ -259,30 +1010,122 @@ pub struct AttributeChange<A: Attribute> {
     identifier: A::Identifier,
     change: A::Value,
     op: AttributeChangeType<A::Value>,
+    /// Optional damage type such as "fire" or "physical", used to select tag-based modifiers
+    #[serde(default)]
+    tag: Option<String>,
 }
 
-impl<S: Status, A: Attribute> Into<ActionElement<A, S>> for AttributeChange<A> {
-    fn into(self) -> ActionElement<A, S> {
-        ActionElement::AttributeChange(self)
+impl<S: Status, A: Attribute> From<AttributeChange<A>> for ActionElement<A, S> {
+    fn from(value: AttributeChange<A>) -> Self {
+        ActionElement::AttributeChange(value)
     }
 }
 
 impl<A: Attribute> AttributeChange<A> {
-    pub(crate) fn apply(&self, attributes: &mut AttributeCollection<A>) {
-        if let Some(a) = attributes.get_attribute_mut(&self.identifier) {
-            match &self.op {
-                AttributeChangeType::Add => a.set_value(a.value() + self.change),
-                AttributeChangeType::Mul => a.set_value(a.value() * self.change),
-                AttributeChangeType::Set => a.set_value(self.change),
-                AttributeChangeType::Average(weight_current, weight_new) => {
-                    a.set_value(
-                        (a.value() * *weight_current + self.change * *weight_new)
-                            / (*weight_current + *weight_new),
-                    );
+    /// Applies the change and reports every attribute it touched, including route layers
+    pub(crate) fn apply(&self, attributes: &mut AttributeCollection<A>) -> Vec<AttributeDelta<A>> {
+        let mut deltas = vec![];
+        let mut change = self.change;
+        let zero = A::Value::default();
+        if self.op == AttributeChangeType::Add && change < zero {
+            let layers = attributes
+                .get_route(&self.identifier)
+                .map(|l| l.to_vec())
+                .unwrap_or_default();
+            for layer in layers {
+                if let Some(a) = attributes.get_attribute_mut(layer.identifier()) {
+                    let old = a.value();
+                    // Bounded by both the layer and the damage still left
+                    let left = zero - change;
+                    let mut take = left * layer.absorb();
+                    if take > left {
+                        take = left;
+                    }
+                    if take > old {
+                        take = if old > zero { old } else { zero };
+                    }
+                    a.set_value(old - take);
+                    let new = a.value();
+                    change = change + (old - new);
+                    deltas.push(AttributeDelta::new(layer.identifier().clone(), old, new));
                 }
             }
         }
+        deltas.extend(self.apply_unrouted(attributes, change));
+        deltas
     }
+
+    /// Applies `change` with the op of this change directly to its attribute
+    fn apply_unrouted(
+        &self,
+        attributes: &mut AttributeCollection<A>,
+        change: A::Value,
+    ) -> Option<AttributeDelta<A>> {
+        let a = attributes.get_attribute_mut(&self.identifier)?;
+        let old = a.value();
+        match &self.op {
+            AttributeChangeType::Add => a.set_value(a.value() + change),
+            AttributeChangeType::Mul => a.set_value(a.value() * change),
+            AttributeChangeType::Set => a.set_value(change),
+            AttributeChangeType::Average(weight_current, weight_new) => {
+                a.set_value(
+                    (a.value() * *weight_current + change * *weight_new)
+                        / (*weight_current + *weight_new),
+                );
+            }
+        }
+        Some(AttributeDelta::new(self.identifier.clone(), old, a.value()))
+    }
+}
+
+/// The realized effect of a change on a single attribute, after routing and clamping
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AttributeDelta<A: Attribute> {
+    identifier: A::Identifier,
+    old: A::Value,
+    new: A::Value,
+}
+
+impl<A: Attribute> AttributeDelta<A> {
+    pub fn new(identifier: A::Identifier, old: A::Value, new: A::Value) -> Self {
+        Self {
+            identifier,
+            old,
+            new,
+        }
+    }
+
+    pub fn identifier(&self) -> &A::Identifier {
+        &self.identifier
+    }
+
+    pub fn old(&self) -> A::Value {
+        self.old
+    }
+
+    pub fn new_value(&self) -> A::Value {
+        self.new
+    }
+
+    /// `new - old`
+    pub fn delta(&self) -> A::Value {
+        self.new - self.old
+    }
+}
+
+/// A realized change on a single character
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "AttributeDelta<A>: Serialize, S: Serialize",
+    deserialize = "AttributeDelta<A>: Deserialize<'de>, S: Deserialize<'de>"
+))]
+pub enum Delta<A: Attribute, S: Status> {
+    Attribute(AttributeDelta<A>),
+    StatusAdded(S),
+    StatusRemoved(S),
+    /// A summon requested by the action, only fulfilled when executed through an
+    /// [`Encounter`](crate::Encounter)
+    Summon(Summon),
 }
 
 pub(crate) type AttributeStatusCollection<'a, A, S> =
@@ -316,6 +1159,7 @@ impl<A: Attribute> AttributeChange<A> {
             identifier,
             change,
             op: AttributeChangeType::Add,
+            tag: None,
         }
     }
 
@@ -324,9 +1168,66 @@ impl<A: Attribute> AttributeChange<A> {
         self
     }
 
+    pub fn with_tag<T: ToString>(mut self, tag: T) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
     pub fn identifier(&self) -> &A::Identifier {
         &self.identifier
     }
+
+    pub fn change(&self) -> A::Value {
+        self.change
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+}
+
+/// Brings a new character into an encounter, built from a template registered with
+/// [`Encounter::add_template`](crate::Encounter::add_template). The summon joins the team of the
+/// actor.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Summon {
+    template: String,
+    duration: Option<u32>,
+    bound: bool,
+}
+
+impl Summon {
+    pub fn new<T: ToString>(template: T) -> Self {
+        Self {
+            template: template.to_string(),
+            duration: None,
+            bound: false,
+        }
+    }
+
+    /// The summon leaves at the start of the summoner's `turns`th turn after it arrived
+    pub fn with_duration(mut self, turns: u32) -> Self {
+        self.duration = Some(turns);
+        self
+    }
+
+    /// The summon leaves when its summoner is defeated or leaves
+    pub fn bound_to_summoner(mut self) -> Self {
+        self.bound = true;
+        self
+    }
+
+    pub fn template(&self) -> &str {
+        self.template.as_str()
+    }
+
+    pub fn duration(&self) -> Option<u32> {
+        self.duration
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.bound
+    }
 }
 
 /// Describes Changing a Status
@@ -337,10 +1238,16 @@ pub enum StatusChange<S: Status> {
 }
 
 impl<S: Status> StatusChange<S> {
-    pub(crate) fn apply(&self, statuses: &mut StatusCollection<S>) {
+    /// Returns the realized delta, or `None` if the collection already matched
+    pub(crate) fn apply<A: Attribute>(
+        &self,
+        statuses: &mut StatusCollection<S>,
+    ) -> Option<Delta<A, S>> {
         match self {
-            Self::Add(s) => statuses.add(s.clone()),
-            Self::Remove(s) => statuses.remove(s),
+            Self::Add(s) => statuses
+                .add(s.clone())
+                .then(|| Delta::StatusAdded(s.clone())),
+            Self::Remove(s) => statuses.remove(s).then(|| Delta::StatusRemoved(s.clone())),
         }
     }
 }
@@ -357,4 +1264,358 @@ impl<T: 'static> AsAny for T {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use crate::RouteLayer;
+
+    #[test]
+    fn test_tag_modifiers() {
+        let (_, mut attributes, statuses, incoming, outgoing) = character(100.);
+        let mut resistant = incoming.clone();
+        resistant.add_by_change_tag("fire", Scale(0.5));
+        let action: Action<Attr, Buff> = Action::new(
+            "Fireball".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![
+                    AttributeChange::new(Id::Hp, -20.).with_tag("fire").into(),
+                    AttributeChange::new(Id::Hp, -20.)
+                        .with_tag("physical")
+                        .into(),
+                ],
+            )),
+        );
+
+        let modified = action.apply_modifiers(
+            &(&attributes, &statuses, &outgoing),
+            &(&attributes, &statuses, &resistant),
+        );
+        let InnerAction::Simple(simple) = &modified else {
+            unreachable!()
+        };
+        let mut statuses = statuses;
+        let mut attribute_collections: Roster<_> = vec![&mut attributes].into();
+        let id = attribute_collections.ids()[0];
+        simple
+            .apply(
+                &mut attribute_collections,
+                &mut vec![&mut statuses].into(),
+                id,
+                id,
+            )
+            .unwrap();
+        assert_eq!(attributes.get_attribute_value(&Id::Hp), Some(70.));
+    }
+
+    #[test]
+    fn test_route_through_barrier() {
+        let (_, mut attributes, ..) = character(100.);
+        attributes.set_attribute_value(&Id::Barrier, 15.);
+        attributes.set_route(
+            Id::Hp,
+            vec![
+                RouteLayer::new(Id::Barrier, 1.),
+                RouteLayer::new(Id::Mana, 0.5),
+            ],
+        );
+
+        let deltas = AttributeChange::<Attr>::new(Id::Hp, -40.).apply(&mut attributes);
+        assert_eq!(
+            deltas,
+            vec![
+                AttributeDelta::new(Id::Barrier, 15., 0.),
+                AttributeDelta::new(Id::Mana, 50., 37.5),
+                AttributeDelta::new(Id::Hp, 100., 87.5),
+            ]
+        );
+
+        // Healing is never routed
+        let deltas = AttributeChange::<Attr>::new(Id::Hp, 10.).apply(&mut attributes);
+        assert_eq!(deltas, vec![AttributeDelta::new(Id::Hp, 87.5, 97.5)]);
+
+        // A layer never takes more than the damage, so it cannot turn it into healing
+        attributes.set_attribute_value(&Id::Barrier, 50.);
+        attributes.set_route(Id::Hp, vec![RouteLayer::new(Id::Barrier, 2.)]);
+        let deltas = AttributeChange::<Attr>::new(Id::Hp, -10.).apply(&mut attributes);
+        assert_eq!(
+            deltas,
+            vec![
+                AttributeDelta::new(Id::Barrier, 50., 40.),
+                AttributeDelta::new(Id::Hp, 97.5, 97.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follow_ups() {
+        let (_, mut actor_attributes, mut actor_statuses, ..) = character(50.);
+        let (_, mut target_attributes, mut target_statuses, ..) = character(10.);
+        // Reflected damage does not trigger the reflect of the actor in turn
+        actor_attributes.add_incoming_follow_up(FollowUp::reflect(Id::Hp, 0.5));
+        target_attributes.add_incoming_follow_up(FollowUp::reflect(Id::Hp, 0.1));
+        let action: InnerAction<Attr, Buff> = InnerAction::Simple(
+            SimpleAction::new(
+                Target::Target,
+                vec![AttributeChange::new(Id::Hp, -20.).into()],
+            )
+            .with_follow_up(FollowUp::lifesteal(Id::Hp, 0.5)),
+        );
+        let mut attribute_collections: Roster<_> =
+            vec![&mut actor_attributes, &mut target_attributes].into();
+        let ids = attribute_collections.ids();
+        let targets = HashMap::from([
+            (Target::Actor, vec![ids[0]]),
+            (Target::Target, vec![ids[1]]),
+        ]);
+
+        // The target only has 10 HP left, so that is all that was dealt
+        let deltas = action
+            .apply(
+                &mut attribute_collections,
+                &mut vec![&mut actor_statuses, &mut target_statuses].into(),
+                &targets,
+                &mut Rng::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            deltas.deltas()[0],
+            (
+                ids[1],
+                Delta::Attribute(AttributeDelta::new(Id::Hp, 10., 0.))
+            )
+        );
+        assert_eq!(
+            deltas.character(ids[0]).unwrap().attribute(&Id::Hp),
+            Some(&AttributeDelta::new(Id::Hp, 50., 54.))
+        );
+        assert_eq!(actor_attributes.get_attribute_value(&Id::Hp), Some(54.));
+        assert_eq!(deltas.deltas().len(), 3);
+    }
+
+    #[test]
+    fn test_conditional_elements() {
+        let (_, mut actor_attributes, mut actor_statuses, ..) = character(100.);
+        let (_, mut target_attributes, mut target_statuses, ..) = character(100.);
+        actor_attributes.set_attribute_value(&Id::Hp, 20.);
+        target_statuses.add(Buff::Wet);
+        let action: SimpleAction<Attr, Buff> = SimpleAction::new(
+            Target::Target,
+            vec![
+                AttributeChange::new(Id::Hp, -10.).into(),
+                ActionElement::Conditional(
+                    Condition::RatioBelow(Subject::Actor, Id::Hp, Id::MaxHp, 0.3),
+                    vec![AttributeChange::new(Id::Hp, -10.).into()],
+                ),
+                ActionElement::Conditional(
+                    Condition::HasStatus(Subject::Receiver, Buff::Wet),
+                    vec![ActionElement::StatusChange(StatusChange::Add(Buff::Frozen))],
+                ),
+                ActionElement::Conditional(
+                    Condition::HasStatus(Subject::Actor, Buff::Wet),
+                    vec![ActionElement::StatusChange(StatusChange::Add(
+                        Buff::Stunned,
+                    ))],
+                ),
+            ],
+        );
+
+        let mut attribute_collections: Roster<_> =
+            vec![&mut actor_attributes, &mut target_attributes].into();
+        let ids = attribute_collections.ids();
+        action
+            .apply(
+                &mut attribute_collections,
+                &mut vec![&mut actor_statuses, &mut target_statuses].into(),
+                ids[0],
+                ids[1],
+            )
+            .unwrap();
+        assert_eq!(target_attributes.get_attribute_value(&Id::Hp), Some(80.));
+        assert!(target_statuses.contains(&Buff::Frozen));
+        assert!(!target_statuses.contains(&Buff::Stunned));
+    }
+
+    #[test]
+    fn test_composite_actions() {
+        let (_, mut actor_attributes, mut actor_statuses, ..) = character(100.);
+        let (_, mut target_attributes, mut target_statuses, ..) = character(100.);
+        let hit = InnerAction::Simple(SimpleAction::new(
+            Target::Target,
+            vec![AttributeChange::new(Id::Hp, -10.).into()],
+        ));
+        let stun = InnerAction::Simple(SimpleAction::new(
+            Target::Target,
+            vec![ActionElement::StatusChange(StatusChange::Add(
+                Buff::Stunned,
+            ))],
+        ));
+        let action: InnerAction<Attr, Buff> = InnerAction::Sequence(vec![
+            InnerAction::Repeat(3, Box::new(hit.clone())),
+            InnerAction::RandomChoice(vec![(0, hit.clone()), (1, stun)]),
+            InnerAction::Conditional(
+                Condition::AttributeBelow(Subject::Receiver, Id::Hp, 50.),
+                Box::new(InnerAction::Sequence(vec![])),
+                Box::new(hit),
+            ),
+        ]);
+        let mut attribute_collections: Roster<_> =
+            vec![&mut actor_attributes, &mut target_attributes].into();
+        let ids = attribute_collections.ids();
+        let targets = HashMap::from([
+            (Target::Actor, vec![ids[0]]),
+            (Target::Target, vec![ids[1]]),
+        ]);
+
+        let deltas = action
+            .apply(
+                &mut attribute_collections,
+                &mut vec![&mut actor_statuses, &mut target_statuses].into(),
+                &targets,
+                &mut Rng::new(7),
+            )
+            .unwrap();
+        assert_eq!(deltas.len(), 5);
+        assert_eq!(target_attributes.get_attribute_value(&Id::Hp), Some(60.));
+        assert!(target_statuses.contains(&Buff::Stunned));
+    }
+
+    #[test]
+    fn test_can_use() {
+        let mut character = character(100.);
+        let fireball: Action<Attr, Buff> = Action::new(
+            "Fireball".to_string(),
+            InnerAction::Simple(SimpleAction::new_empty(Target::Target)),
+        )
+        .with_tag("spell")
+        .with_cost(AttributeChange::new(Id::Mana, -80.));
+        let strike: Action<Attr, Buff> = Action::new(
+            "Strike".to_string(),
+            InnerAction::Simple(SimpleAction::new_empty(Target::Target)),
+        );
+        assert!(can_use(&strike, &character).is_ok());
+        assert!(matches!(
+            can_use(&fireball, &character),
+            Err(ActionError::InsufficientAttribute { .. })
+        ));
+
+        character.2.add(Buff::Frozen);
+        assert_eq!(
+            can_use(&fireball, &character),
+            Err(ActionError::Blocked(Buff::Frozen))
+        );
+        assert!(can_use(&strike, &character).is_ok());
+        character.2.add(Buff::Stunned);
+        assert_eq!(
+            can_use(&strike, &character),
+            Err(ActionError::Blocked(Buff::Stunned))
+        );
+    }
+
+    #[test]
+    fn test_action_tag_modifiers() {
+        let (_, attributes, statuses, incoming, mut outgoing) = character(100.);
+        outgoing.add_by_action_tag("fire", Scale(1.2));
+        outgoing.add_by_action_tag("spell", Scale(0.5));
+        let action: Action<Attr, Buff> = Action::new(
+            "Fireball".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![AttributeChange::new(Id::Hp, -20.).into()],
+            )),
+        )
+        .with_tag("fire");
+
+        let modified = action.apply_modifiers(
+            &(&attributes, &statuses, &outgoing),
+            &(&attributes, &statuses, &incoming),
+        );
+        let InnerAction::Simple(simple) = &modified else {
+            unreachable!()
+        };
+        let ActionElement::AttributeChange(change) = &simple.elements[0] else {
+            unreachable!()
+        };
+        assert_eq!(change.change(), -24.);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let (_, mut actor_attributes, mut actor_statuses, ..) = character(100.);
+        let mut target_attributes = AttributeCollection::new();
+        target_attributes.add_attribute(Id::Hp, Attr(100.));
+        target_attributes.add_attribute(Id::Barrier, Attr(5.));
+        target_attributes.set_route(Id::Hp, vec![RouteLayer::new(Id::Barrier, 1.)]);
+        let mut target_statuses = StatusCollection::default();
+        let mut attribute_collections: Roster<_> =
+            vec![&mut actor_attributes, &mut target_attributes].into();
+        let mut status_collections: Roster<_> =
+            vec![&mut actor_statuses, &mut target_statuses].into();
+        let ids = attribute_collections.ids();
+        let targets = HashMap::from([
+            (Target::Actor, vec![ids[0]]),
+            (Target::Target, vec![ids[1]]),
+        ]);
+        let hit = |target| {
+            InnerAction::Simple(SimpleAction::new(
+                target,
+                vec![
+                    AttributeChange::new(Id::Hp, -20.).into(),
+                    ActionElement::StatusChange(StatusChange::Add(Buff::Wet)),
+                ],
+            ))
+        };
+        let mut apply = |action: InnerAction<Attr, Buff>| {
+            action.apply(
+                &mut attribute_collections,
+                &mut status_collections,
+                &targets,
+                &mut Rng::default(),
+            )
+        };
+
+        let action = InnerAction::Sequence(vec![hit(Target::Target), hit(Target::AllEnemies)]);
+        assert_eq!(
+            apply(action),
+            Err(ActionError::UnresolvedTarget(Target::AllEnemies))
+        );
+        let mut drain = hit(Target::Target);
+        if let InnerAction::Simple(a) = &mut drain {
+            a.elements.push(AttributeChange::new(Id::Mana, -10.).into());
+        }
+        assert_eq!(
+            drain.apply_strict(
+                &mut attribute_collections,
+                &mut status_collections,
+                &targets,
+                &mut Rng::default()
+            ),
+            Err(ActionError::MissingReceiverAttribute {
+                character: ids[1],
+                identifier: Id::Mana
+            })
+        );
+        assert_eq!(
+            attribute_collections[ids[1]].get_attribute_value(&Id::Hp),
+            Some(100.)
+        );
+        assert_eq!(
+            attribute_collections[ids[1]].get_attribute_value(&Id::Barrier),
+            Some(5.)
+        );
+        assert!(!status_collections[ids[1]].contains(&Buff::Wet));
+
+        // Outside of strict mode the missing attribute is skipped
+        let result = drain
+            .apply(
+                &mut attribute_collections,
+                &mut status_collections,
+                &targets,
+                &mut Rng::default(),
+            )
+            .unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(target_attributes.get_attribute_value(&Id::Hp), Some(85.));
+        assert!(target_statuses.contains(&Buff::Wet));
+    }
+}
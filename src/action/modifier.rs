@@ -1,6 +1,8 @@
 use crate::action::AttributeStatusCollection;
+use crate::hash::{debug_hash, stable_hash, unordered_hash};
 use crate::{Action, Attribute, AttributeChange, Status, StatusChange};
 use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
 
@@ -30,14 +32,162 @@ pub trait Modifier: Default + Debug + DynClone {
 pub type IncomingModifierCollection<M> = ModifierCollection<M>;
 pub type OutgoingModifierCollection<M> = ModifierCollection<M>;
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "M: Serialize, <M::Attr as Attribute>::Identifier: Serialize",
+    deserialize = "M: Deserialize<'de>, <M::Attr as Attribute>::Identifier: Deserialize<'de>"
+))]
 pub struct ModifierCollection<M: Modifier> {
+    #[serde(serialize_with = "crate::hash::sorted_map")]
     by_attribute_name: HashMap<<M::Attr as Attribute>::Identifier, Vec<M>>,
+    #[serde(serialize_with = "crate::hash::sorted_map")]
     by_action_name: HashMap<String, Vec<M>>,
+    #[serde(serialize_with = "crate::hash::sorted_map")]
+    by_action_tag: HashMap<String, Vec<M>>,
+    #[serde(serialize_with = "crate::hash::sorted_map")]
+    by_change_tag: HashMap<String, Vec<M>>,
+    #[serde(default, serialize_with = "crate::hash::sorted_map")]
+    cost_by_action_name: HashMap<String, Vec<M>>,
+    #[serde(default, serialize_with = "crate::hash::sorted_map")]
+    cost_by_action_tag: HashMap<String, Vec<M>>,
     complex: Vec<Box<M>>,
 }
 
+impl<M: Modifier> Clone for ModifierCollection<M> {
+    fn clone(&self) -> Self {
+        let clone_all = |v: &Vec<M>| v.iter().map(dyn_clone::clone).collect();
+        let clone_map = |map: &HashMap<String, Vec<M>>| {
+            map.iter().map(|(k, v)| (k.clone(), clone_all(v))).collect()
+        };
+        Self {
+            by_attribute_name: self
+                .by_attribute_name
+                .iter()
+                .map(|(k, v)| (k.clone(), clone_all(v)))
+                .collect(),
+            by_action_name: clone_map(&self.by_action_name),
+            by_action_tag: clone_map(&self.by_action_tag),
+            by_change_tag: clone_map(&self.by_change_tag),
+            cost_by_action_name: clone_map(&self.cost_by_action_name),
+            cost_by_action_tag: clone_map(&self.cost_by_action_tag),
+            complex: self
+                .complex
+                .iter()
+                .map(|m| Box::new(dyn_clone::clone(m.as_ref())))
+                .collect(),
+        }
+    }
+}
+
 impl<M: Modifier> ModifierCollection<M> {
+    pub fn add_by_attribute_name(
+        &mut self,
+        identifier: <M::Attr as Attribute>::Identifier,
+        modifier: M,
+    ) {
+        self.by_attribute_name
+            .entry(identifier)
+            .or_default()
+            .push(modifier);
+    }
+
+    pub fn add_by_action_name<T: ToString>(&mut self, name: T, modifier: M) {
+        self.by_action_name
+            .entry(name.to_string())
+            .or_default()
+            .push(modifier);
+    }
+
+    /// Modifiers applied to every change of actions tagged `tag`, e.g. a bonus to all spells
+    pub fn add_by_action_tag<T: ToString>(&mut self, tag: T, modifier: M) {
+        self.by_action_tag
+            .entry(tag.to_string())
+            .or_default()
+            .push(modifier);
+    }
+
+    /// Modifiers applied to every [`AttributeChange`] carrying `tag`, e.g. fire resistances
+    pub fn add_by_change_tag<T: ToString>(&mut self, tag: T, modifier: M) {
+        self.by_change_tag
+            .entry(tag.to_string())
+            .or_default()
+            .push(modifier);
+    }
+
+    /// Modifiers applied to the costs of the action called `name` only, e.g. a mana cost
+    /// reduction. Costs are not affected by any other modifier.
+    pub fn add_cost_by_action_name<T: ToString>(&mut self, name: T, modifier: M) {
+        self.cost_by_action_name
+            .entry(name.to_string())
+            .or_default()
+            .push(modifier);
+    }
+
+    /// Modifiers applied to the costs of actions tagged `tag`, e.g. cheaper spells
+    pub fn add_cost_by_action_tag<T: ToString>(&mut self, tag: T, modifier: M) {
+        self.cost_by_action_tag
+            .entry(tag.to_string())
+            .or_default()
+            .push(modifier);
+    }
+
+    pub fn add_complex(&mut self, modifier: M) {
+        self.complex.push(Box::new(modifier));
+    }
+
+    /// A hash of every modifier, through its `Debug` output. Modifiers under the same key are
+    /// hashed in order, since that is the order they are applied in, but the order of the keys
+    /// does not matter.
+    pub fn state_hash(&self) -> u64 {
+        let modifiers = |v: &[M]| v.iter().map(debug_hash).collect::<Vec<_>>();
+        let by_key = |map: &HashMap<String, Vec<M>>| {
+            unordered_hash(map.iter().map(|(k, v)| stable_hash(&(k, modifiers(v)))))
+        };
+        let by_attribute_name = unordered_hash(
+            self.by_attribute_name
+                .iter()
+                .map(|(k, v)| stable_hash(&(k, modifiers(v)))),
+        );
+        let complex: Vec<_> = self
+            .complex
+            .iter()
+            .map(|m| debug_hash(m.as_ref()))
+            .collect();
+        stable_hash(&(
+            by_attribute_name,
+            by_key(&self.by_action_name),
+            by_key(&self.by_action_tag),
+            by_key(&self.by_change_tag),
+            by_key(&self.cost_by_action_name),
+            by_key(&self.cost_by_action_tag),
+            complex,
+        ))
+    }
+
+    /// `cost` after the cost modifiers of `action`
+    pub(crate) fn generate_cost<S>(
+        &self,
+        cost: &AttributeChange<M::Attr>,
+        action: &Action<M::Attr, S>,
+    ) -> AttributeChange<M::Attr>
+    where
+        S: Status,
+    {
+        let mut result = cost.clone();
+        self.cost_by_action_name
+            .get(action.name())
+            .into_iter()
+            .chain(
+                action
+                    .tags()
+                    .iter()
+                    .filter_map(|tag| self.cost_by_action_tag.get(tag)),
+            )
+            .flatten()
+            .for_each(|x| result.change = x.apply(result.change));
+        result
+    }
+
     pub(crate) fn generate_attribute_change<S>(
         &self,
         attribute_change: &AttributeChange<M::Attr>,
@@ -53,10 +203,23 @@ impl<M: Modifier> ModifierCollection<M> {
             v.iter()
                 .for_each(|x| result.change = x.apply(result.change));
         }
+        if let Some(v) = attribute_change
+            .tag()
+            .and_then(|tag| self.by_change_tag.get(tag))
+        {
+            v.iter()
+                .for_each(|x| result.change = x.apply(result.change));
+        }
         if let Some(v) = self.by_action_name.get(action.name()) {
             v.iter()
                 .for_each(|x| result.change = x.apply(result.change));
         }
+        action
+            .tags()
+            .iter()
+            .filter_map(|tag| self.by_action_tag.get(tag))
+            .flatten()
+            .for_each(|x| result.change = x.apply(result.change));
         self.complex.iter().for_each(|x| {
             result.change = x.apply_if_applicable(
                 result.change,
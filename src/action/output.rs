@@ -1,5 +1,7 @@
-use crate::CharacterBase;
+use crate::{CharacterBase, CharacterId, Roster};
 use itertools::concat;
+use std::borrow::Borrow;
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub struct ActionOutput {
@@ -8,6 +10,7 @@ pub struct ActionOutput {
 }
 
 impl ActionOutput {
+    #[allow(dead_code)]
     pub(crate) fn new<T: ToString>(display_name: Option<&T>, text: &str) -> ActionOutput {
         Self {
             display_name: display_name.map(|x| x.to_string()),
@@ -49,18 +52,25 @@ impl ActionText {
 }
 
 impl ActionText {
-    pub fn format(&self, characters: &[CharacterBase], actor: usize, targets: &[usize]) -> String {
+    /// Fills in the names of the actor and the first target. Placeholders for characters that
+    /// are not in the roster are left as they are.
+    pub fn format<B: Borrow<CharacterBase>>(
+        &self,
+        characters: &Roster<B>,
+        actor: CharacterId,
+        targets: &[CharacterId],
+    ) -> String {
         match self {
             Self::Simple(s) => s.clone(),
             Self::Replace(s, r) => {
                 let mut result = s.clone();
                 for s in r {
                     let with = match s {
-                        Replace::Actor => characters.get(actor).map(|x| x.name()),
+                        Replace::Actor => characters.get(actor).map(|x| x.borrow().name()),
                         Replace::Target => targets
-                            .get(0)
+                            .first()
                             .and_then(|i| characters.get(*i))
-                            .map(|x| x.name()),
+                            .map(|x| x.borrow().name()),
                         _ => None,
                     };
                     if let Some(w) = with {
@@ -92,12 +102,12 @@ pub enum Replace {
     Undefined,
 }
 
-impl ToString for Replace {
-    fn to_string(&self) -> String {
+impl Display for Replace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Replace::Actor => "{actor}".to_string(),
-            Replace::Target => "{target}".to_string(),
-            _ => String::new(),
+            Replace::Actor => write!(f, "{{actor}}"),
+            Replace::Target => write!(f, "{{target}}"),
+            _ => Ok(()),
         }
     }
 }
@@ -108,9 +118,24 @@ mod tests {
 
     #[test]
     fn test_format() {
-        let _initial_string = "aaa {target} bbb {actor}{target}";
-        let r = "".to_string();
+        let initial_string = "aaa {target} bbb {actor}{target}";
+        let mut characters: Roster<_> = vec![
+            CharacterBase::new_base("ACTOR"),
+            CharacterBase::new_base("TARGET"),
+        ]
+        .into();
+        let ids = characters.ids();
+        let text = ActionText::Replace(
+            initial_string.to_string(),
+            vec![Replace::Target, Replace::Actor],
+        );
+        let r = text.format(&characters, ids[0], &[ids[1]]);
         assert_eq!("aaa TARGET bbb ACTORTARGET", r.as_str());
+
+        characters.remove(ids[1]);
+        characters.insert(CharacterBase::new_base("OTHER"));
+        let r = text.format(&characters, ids[0], &[ids[1]]);
+        assert_eq!("aaa {target} bbb ACTOR{target}", r.as_str());
     }
 
     #[test]
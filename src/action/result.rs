@@ -0,0 +1,286 @@
+use crate::{
+    Attribute, AttributeCollection, AttributeDelta, CharacterDeltas, CharacterId, Delta, Roster,
+    Status, StatusCollection, Summon,
+};
+use serde::{Deserialize, Serialize};
+
+/// Everything an application of an action changed, in the order it happened. Besides the raw
+/// deltas, [`ActionResult::character`] sums them up into the net change of each character.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Delta<A, S>: Serialize",
+    deserialize = "Delta<A, S>: Deserialize<'de>"
+))]
+pub struct ActionResult<A: Attribute, S: Status> {
+    deltas: CharacterDeltas<A, S>,
+}
+
+impl<A: Attribute, S: Status> Default for ActionResult<A, S> {
+    fn default() -> Self {
+        Self { deltas: vec![] }
+    }
+}
+
+impl<A: Attribute, S: Status> ActionResult<A, S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, character: CharacterId, delta: Delta<A, S>) {
+        self.deltas.push((character, delta));
+    }
+
+    /// Appends the deltas of `other`, which happened after those of `self`
+    pub fn extend(&mut self, other: ActionResult<A, S>) {
+        self.deltas.extend(other.deltas);
+    }
+
+    /// Every realized delta along with the character it happened on
+    pub fn deltas(&self) -> &[(CharacterId, Delta<A, S>)] {
+        &self.deltas
+    }
+
+    pub fn into_deltas(self) -> CharacterDeltas<A, S> {
+        self.deltas
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Every affected character, in order of first appearance
+    pub fn characters(&self) -> Vec<CharacterId> {
+        let mut result = vec![];
+        for (id, _) in &self.deltas {
+            if !result.contains(id) {
+                result.push(*id);
+            }
+        }
+        result
+    }
+
+    /// The net change of `character`, or `None` if the action did not affect it
+    pub fn character(&self, character: CharacterId) -> Option<CharacterResult<A, S>> {
+        let mut result = CharacterResult::new(character);
+        let mut statuses: Vec<(&S, bool, bool)> = vec![];
+        let mut affected = false;
+        for (_, delta) in self.deltas.iter().filter(|(id, _)| *id == character) {
+            affected = true;
+            match delta {
+                Delta::Attribute(a) => {
+                    match result
+                        .attributes
+                        .iter_mut()
+                        .find(|r| r.identifier() == a.identifier())
+                    {
+                        Some(r) => {
+                            *r = AttributeDelta::new(a.identifier().clone(), r.old(), a.new_value())
+                        }
+                        None => result.attributes.push(a.clone()),
+                    }
+                }
+                Delta::StatusAdded(s) | Delta::StatusRemoved(s) => {
+                    let added = matches!(delta, Delta::StatusAdded(_));
+                    match statuses.iter_mut().find(|(status, ..)| *status == s) {
+                        Some((_, _, last)) => *last = added,
+                        None => statuses.push((s, added, added)),
+                    }
+                }
+                Delta::Summon(s) => result.summons.push(s.clone()),
+            }
+        }
+        // A status only changed if it ended up the way its first change put it
+        for (status, first, last) in statuses {
+            match (first, last) {
+                (true, true) => result.added.push(status.clone()),
+                (false, false) => result.removed.push(status.clone()),
+                _ => {}
+            }
+        }
+        affected.then_some(result)
+    }
+
+    /// The net change of every affected character, in order of first appearance
+    pub fn summary(&self) -> Vec<CharacterResult<A, S>> {
+        self.characters()
+            .into_iter()
+            .filter_map(|id| self.character(id))
+            .collect()
+    }
+
+    /// Restores every attribute and status the deltas touched to how it was before, latest
+    /// change first. Attributes are set to their recorded old value, so ops such as `Mul` or
+    /// `Average` are rewound exactly. Summons are not undone.
+    pub fn revert(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+    ) {
+        for (id, delta) in self.deltas.iter().rev() {
+            match delta {
+                Delta::Attribute(a) => {
+                    if let Some(attributes) = attribute_collections.get_mut(*id) {
+                        attributes.set_attribute_value(a.identifier(), a.old());
+                    }
+                }
+                Delta::StatusAdded(s) => {
+                    if let Some(statuses) = status_collections.get_mut(*id) {
+                        statuses.remove(s);
+                    }
+                }
+                Delta::StatusRemoved(s) => {
+                    if let Some(statuses) = status_collections.get_mut(*id) {
+                        statuses.add(s.clone());
+                    }
+                }
+                Delta::Summon(_) => {}
+            }
+        }
+    }
+
+    /// Sets everything the deltas touched to how they left it, e.g. to redo an action after
+    /// [`ActionResult::revert`] without rolling random choices again
+    pub fn reapply(
+        &self,
+        attribute_collections: &mut Roster<&mut AttributeCollection<A>>,
+        status_collections: &mut Roster<&mut StatusCollection<S>>,
+    ) {
+        for (id, delta) in &self.deltas {
+            match delta {
+                Delta::Attribute(a) => {
+                    if let Some(attributes) = attribute_collections.get_mut(*id) {
+                        attributes.set_attribute_value(a.identifier(), a.new_value());
+                    }
+                }
+                Delta::StatusAdded(s) => {
+                    if let Some(statuses) = status_collections.get_mut(*id) {
+                        statuses.add(s.clone());
+                    }
+                }
+                Delta::StatusRemoved(s) => {
+                    if let Some(statuses) = status_collections.get_mut(*id) {
+                        statuses.remove(s);
+                    }
+                }
+                Delta::Summon(_) => {}
+            }
+        }
+    }
+
+    /// Summons requested by the action along with the character that requested them
+    pub fn summons(&self) -> impl Iterator<Item = (CharacterId, &Summon)> {
+        self.deltas.iter().filter_map(|(id, d)| match d {
+            Delta::Summon(s) => Some((*id, s)),
+            _ => None,
+        })
+    }
+}
+
+impl<A: Attribute, S: Status> From<CharacterDeltas<A, S>> for ActionResult<A, S> {
+    fn from(deltas: CharacterDeltas<A, S>) -> Self {
+        Self { deltas }
+    }
+}
+
+impl<A: Attribute, S: Status> IntoIterator for ActionResult<A, S> {
+    type Item = (CharacterId, Delta<A, S>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deltas.into_iter()
+    }
+}
+
+/// The net change of a single character. Attributes hold their value from before the first
+/// and after the last change, statuses that were added and removed again are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterResult<A: Attribute, S: Status> {
+    character: CharacterId,
+    attributes: Vec<AttributeDelta<A>>,
+    added: Vec<S>,
+    removed: Vec<S>,
+    summons: Vec<Summon>,
+}
+
+impl<A: Attribute, S: Status> CharacterResult<A, S> {
+    fn new(character: CharacterId) -> Self {
+        Self {
+            character,
+            attributes: vec![],
+            added: vec![],
+            removed: vec![],
+            summons: vec![],
+        }
+    }
+
+    pub fn character(&self) -> CharacterId {
+        self.character
+    }
+
+    pub fn attributes(&self) -> &[AttributeDelta<A>] {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, identifier: &A::Identifier) -> Option<&AttributeDelta<A>> {
+        self.attributes
+            .iter()
+            .find(|a| a.identifier() == identifier)
+    }
+
+    pub fn statuses_added(&self) -> &[S] {
+        &self.added
+    }
+
+    pub fn statuses_removed(&self) -> &[S] {
+        &self.removed
+    }
+
+    pub fn summons(&self) -> &[Summon] {
+        &self.summons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use crate::Roster;
+
+    #[test]
+    fn test_character_result() {
+        let ids = Roster::from(vec![(), ()]).ids();
+        let mut result: ActionResult<Attr, Buff> = ActionResult::new();
+        result.push(
+            ids[0],
+            Delta::Attribute(AttributeDelta::new(Id::Hp, 100., 90.)),
+        );
+        result.push(ids[0], Delta::StatusAdded(Buff::Wet));
+        result.push(
+            ids[0],
+            Delta::Attribute(AttributeDelta::new(Id::Mana, 50., 40.)),
+        );
+        result.push(
+            ids[0],
+            Delta::Attribute(AttributeDelta::new(Id::Hp, 90., 75.)),
+        );
+        result.push(ids[0], Delta::StatusRemoved(Buff::Wet));
+        result.push(ids[0], Delta::StatusRemoved(Buff::Frozen));
+
+        let summary = result.character(ids[0]).unwrap();
+        assert_eq!(
+            summary.attributes(),
+            &[
+                AttributeDelta::new(Id::Hp, 100., 75.),
+                AttributeDelta::new(Id::Mana, 50., 40.),
+            ]
+        );
+        assert!(summary.statuses_added().is_empty());
+        assert_eq!(summary.statuses_removed(), &[Buff::Frozen]);
+        assert_eq!(result.character(ids[1]), None);
+        assert_eq!(result.summary(), vec![summary]);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    Action, ActionElement, Attribute, AttributeChange, AttributeChangeType, AttributeCollection,
+    Character, Condition, InnerAction, Modifier, SimpleAction, Status, Target,
+};
+use thiserror::Error;
+
+/// A problem with an action that would make it fail or do nothing when executed
+#[derive(Debug, Clone, Error)]
+pub enum ValidationError<A: Attribute> {
+    #[error("the character has no {0:?} attribute")]
+    UnknownAttribute(A::Identifier),
+    #[error("{0:?} never selects any characters")]
+    ImpossibleTarget(Target<A::Identifier>),
+    #[error("a random choice has no option with a positive weight")]
+    EmptyRandomChoice,
+    #[error("custom actions cannot be applied")]
+    CustomAction,
+    #[error("the average on {0:?} has weights that add up to zero")]
+    ZeroAverageWeights(A::Identifier),
+    #[error("the cost on {0:?} is not an `Add`")]
+    NonAdditiveCost(A::Identifier),
+}
+
+impl<A: Attribute> PartialEq for ValidationError<A> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::UnknownAttribute(a), Self::UnknownAttribute(b))
+            | (Self::ZeroAverageWeights(a), Self::ZeroAverageWeights(b))
+            | (Self::NonAdditiveCost(a), Self::NonAdditiveCost(b)) => a == b,
+            (Self::ImpossibleTarget(a), Self::ImpossibleTarget(b)) => a == b,
+            (Self::EmptyRandomChoice, Self::EmptyRandomChoice)
+            | (Self::CustomAction, Self::CustomAction) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Checks `action` against the attributes of `character`, which is assumed to have the same
+/// attributes as every character the action is used on, and returns every problem found.
+/// Meant to be run when actions are loaded, so that a typo in a data file does not go unnoticed
+/// until the action silently does nothing.
+pub fn validate<A, S, M>(
+    action: &Action<A, S>,
+    character: &Character<A, S, M>,
+) -> Vec<ValidationError<A>>
+where
+    A: Attribute,
+    S: Status,
+    M: Modifier<Attr = A>,
+{
+    let mut validator = Validator {
+        attributes: &character.1,
+        errors: vec![],
+    };
+    for cost in action.costs() {
+        validator.change(cost);
+        if cost.op != AttributeChangeType::Add {
+            validator.push(ValidationError::NonAdditiveCost(cost.identifier.clone()));
+        }
+    }
+    validator.inner(action.inner());
+    validator.errors
+}
+
+struct Validator<'a, A: Attribute> {
+    attributes: &'a AttributeCollection<A>,
+    errors: Vec<ValidationError<A>>,
+}
+
+impl<A: Attribute> Validator<'_, A> {
+    fn push(&mut self, error: ValidationError<A>) {
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    fn identifier(&mut self, identifier: &A::Identifier) {
+        if self.attributes.get_attribute(identifier).is_none() {
+            self.push(ValidationError::UnknownAttribute(identifier.clone()));
+        }
+    }
+
+    fn target(&mut self, target: &Target<A::Identifier>) {
+        match target {
+            Target::RandomEnemy(0) => self.push(ValidationError::ImpossibleTarget(target.clone())),
+            Target::LowestAttribute(identifier) => self.identifier(identifier),
+            _ => {}
+        }
+    }
+
+    fn inner<S: Status>(&mut self, action: &InnerAction<A, S>) {
+        match action {
+            InnerAction::Simple(a) => self.simple(a),
+            InnerAction::SelfOther(a1, a2) => {
+                self.simple(a1);
+                self.simple(a2);
+            }
+            InnerAction::Custom(_) => self.push(ValidationError::CustomAction),
+            InnerAction::Sequence(v) => v.iter().for_each(|a| self.inner(a)),
+            InnerAction::Repeat(_, a) => self.inner(a),
+            InnerAction::RandomChoice(v) => {
+                if v.iter().all(|(w, _)| *w == 0) {
+                    self.push(ValidationError::EmptyRandomChoice);
+                }
+                v.iter().for_each(|(_, a)| self.inner(a));
+            }
+            InnerAction::Conditional(c, a1, a2) => {
+                self.condition(c);
+                self.inner(a1);
+                self.inner(a2);
+            }
+        }
+    }
+
+    fn simple<S: Status>(&mut self, action: &SimpleAction<A, S>) {
+        self.target(&action.target);
+        action.elements.iter().for_each(|e| self.element(e));
+        for f in &action.follow_ups {
+            self.target(&f.target);
+            self.identifier(&f.watch);
+            self.identifier(&f.identifier);
+        }
+    }
+
+    fn element<S: Status>(&mut self, element: &ActionElement<A, S>) {
+        match element {
+            ActionElement::AttributeChange(a) => self.change(a),
+            ActionElement::Conditional(c, v) => {
+                self.condition(c);
+                v.iter().for_each(|e| self.element(e));
+            }
+            ActionElement::StatusChange(_) | ActionElement::Summon(_) => {}
+        }
+    }
+
+    fn change(&mut self, change: &AttributeChange<A>) {
+        self.identifier(&change.identifier);
+        if let AttributeChangeType::Average(w1, w2) = change.op {
+            if w1 + w2 == A::Value::default() {
+                self.push(ValidationError::ZeroAverageWeights(
+                    change.identifier.clone(),
+                ));
+            }
+        }
+    }
+
+    fn condition<S: Status>(&mut self, condition: &Condition<A, S>) {
+        match condition {
+            Condition::Always | Condition::HasStatus(..) => {}
+            Condition::AttributeBelow(_, i, _) | Condition::AttributeAbove(_, i, _) => {
+                self.identifier(i)
+            }
+            Condition::RatioBelow(_, i, of, _) | Condition::RatioAbove(_, i, of, _) => {
+                self.identifier(i);
+                self.identifier(of);
+            }
+            Condition::Not(c) => self.condition(c),
+            Condition::All(v) | Condition::Any(v) => v.iter().for_each(|c| self.condition(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use crate::{FollowUp, Subject};
+
+    #[test]
+    fn test_validate() {
+        let mut character = character(100.);
+        let valid: Action<Attr, Buff> = Action::new(
+            "Strike".to_string(),
+            InnerAction::Simple(
+                SimpleAction::new(
+                    Target::LowestAttribute(Id::Hp),
+                    vec![AttributeChange::new(Id::Hp, -10.).into()],
+                )
+                .with_follow_up(FollowUp::lifesteal(Id::Hp, 0.5)),
+            ),
+        )
+        .with_cost(AttributeChange::new(Id::Mana, -5.));
+        assert_eq!(validate(&valid, &character), vec![]);
+
+        let invalid: Action<Attr, Buff> = Action::new(
+            "Typo".to_string(),
+            InnerAction::RandomChoice(vec![(
+                0,
+                InnerAction::Simple(SimpleAction::new(
+                    Target::RandomEnemy(0),
+                    vec![ActionElement::Conditional(
+                        Condition::AttributeBelow(Subject::Actor, Id::Barrier, 10.),
+                        vec![AttributeChange::new(Id::Hp, 5.)
+                            .with_op(AttributeChangeType::Average(1., -1.))
+                            .into()],
+                    )],
+                )),
+            )]),
+        )
+        .with_cost(AttributeChange::new(Id::Mana, 0.).with_op(AttributeChangeType::Set));
+        character.1 = AttributeCollection::new();
+        character.1.add_attribute(Id::Hp, Attr(100.));
+        assert_eq!(
+            validate(&invalid, &character),
+            vec![
+                ValidationError::UnknownAttribute(Id::Mana),
+                ValidationError::NonAdditiveCost(Id::Mana),
+                ValidationError::EmptyRandomChoice,
+                ValidationError::ImpossibleTarget(Target::RandomEnemy(0)),
+                ValidationError::UnknownAttribute(Id::Barrier),
+                ValidationError::ZeroAverageWeights(Id::Hp),
+            ]
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use crate::action::modifier::{IncomingModifierCollection, Modifier, OutgoingModifierCollection};
-use std::collections::{HashMap, HashSet};
+use crate::hash::{debug_hash, stable_hash, unordered_hash};
+use crate::{Cooldowns, FollowUp, Team, ValidationError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::{Add, Div, Mul, Sub};
@@ -16,9 +19,12 @@ pub type Character<A, S, M> = (
     OutgoingModifierCollection<M>,
 );
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CharacterBase {
     name: String,
+    team: Team,
+    defeated: bool,
+    cooldowns: Cooldowns,
 }
 
 impl CharacterBase {
@@ -26,6 +32,30 @@ impl CharacterBase {
         self.name.as_ref()
     }
 
+    pub fn team(&self) -> Team {
+        self.team
+    }
+
+    pub fn set_team(&mut self, team: Team) {
+        self.team = team;
+    }
+
+    /// Whether the character is out of combat, see [`crate::Encounter::set_defeat_rule`]
+    pub fn is_defeated(&self) -> bool {
+        self.defeated
+    }
+
+    pub(crate) fn set_defeated(&mut self, defeated: bool) {
+        self.defeated = defeated;
+    }
+
+    pub fn cooldowns(&self) -> &Cooldowns {
+        &self.cooldowns
+    }
+
+    pub fn cooldowns_mut(&mut self) -> &mut Cooldowns {
+        &mut self.cooldowns
+    }
 
     pub fn new<A, S, M>(name: &str) -> Character<A, S, M>
     where
@@ -41,9 +71,12 @@ impl CharacterBase {
             OutgoingModifierCollection::default(),
         )
     }
-    fn new_base(name: &str) -> Self {
+    pub(crate) fn new_base(name: &str) -> Self {
         Self {
             name: name.to_string(),
+            team: Team::default(),
+            defeated: false,
+            cooldowns: Cooldowns::default(),
         }
     }
 }
@@ -54,10 +87,21 @@ impl CharacterBase {
 
 pub trait AttributeIdentifier: Debug + Default + Clone + Hash + PartialEq + Eq {}
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "A: Serialize, A::Identifier: Serialize, RouteLayer<A>: Serialize, \
+                 FollowUp<A>: Serialize",
+    deserialize = "A: Deserialize<'de>, A::Identifier: Deserialize<'de>, \
+                   RouteLayer<A>: Deserialize<'de>, FollowUp<A>: Deserialize<'de>"
+))]
 pub struct AttributeCollection<A: Attribute> {
     attributes: Vec<A>,
+    #[serde(serialize_with = "crate::hash::sorted_map")]
     attribute_map: HashMap<A::Identifier, usize>,
+    #[serde(serialize_with = "crate::hash::sorted_map")]
+    routes: HashMap<A::Identifier, Vec<RouteLayer<A>>>,
+    #[serde(default)]
+    incoming_follow_ups: Vec<FollowUp<A>>,
 }
 
 impl<A: Attribute> AttributeCollection<A> {
@@ -65,6 +109,8 @@ impl<A: Attribute> AttributeCollection<A> {
         Self {
             attributes: vec![],
             attribute_map: HashMap::new(),
+            routes: HashMap::new(),
+            incoming_follow_ups: vec![],
         }
     }
 
@@ -89,11 +135,99 @@ impl<A: Attribute> AttributeCollection<A> {
             .and_then(|idx| self.attributes.get_mut(*idx))
     }
 
+    /// Every attribute along with its identifier, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&A::Identifier, &A)> {
+        self.attribute_map
+            .iter()
+            .map(|(identifier, idx)| (identifier, &self.attributes[*idx]))
+    }
+
+    /// Does nothing if there is no attribute with `identifier`, see
+    /// [`AttributeCollection::try_set_attribute_value`]
     pub fn set_attribute_value(&mut self, identifier: &A::Identifier, value: A::Value) {
         if let Some(a) = self.get_attribute_mut(identifier) {
             a.set_value(value);
         }
     }
+
+    /// Like [`AttributeCollection::set_attribute_value`], but reports an unknown `identifier`
+    pub fn try_set_attribute_value(
+        &mut self,
+        identifier: &A::Identifier,
+        value: A::Value,
+    ) -> Result<(), ValidationError<A>> {
+        let a = self
+            .get_attribute_mut(identifier)
+            .ok_or_else(|| ValidationError::UnknownAttribute(identifier.clone()))?;
+        a.set_value(value);
+        Ok(())
+    }
+
+    /// A hash of every attribute value, route and incoming follow-up that does not depend on the
+    /// order attributes were added in, see [`StableHasher`](crate::StableHasher)
+    pub fn state_hash(&self) -> u64 {
+        let attributes = unordered_hash(
+            self.iter()
+                .map(|(identifier, a)| stable_hash(&(identifier, debug_hash(&a.value())))),
+        );
+        let routes = unordered_hash(self.routes.iter().map(|(identifier, layers)| {
+            let layers: Vec<_> = layers
+                .iter()
+                .map(|l| (l.identifier(), debug_hash(&l.absorb())))
+                .collect();
+            stable_hash(&(identifier, layers))
+        }));
+        let follow_ups: Vec<_> = self.incoming_follow_ups.iter().map(debug_hash).collect();
+        stable_hash(&(attributes, routes, follow_ups))
+    }
+
+    /// Routes incoming damage on `identifier` through `layers` first, see [`RouteLayer`]
+    pub fn set_route(&mut self, identifier: A::Identifier, layers: Vec<RouteLayer<A>>) {
+        self.routes.insert(identifier, layers);
+    }
+
+    pub fn remove_route(&mut self, identifier: &A::Identifier) {
+        self.routes.remove(identifier);
+    }
+
+    pub fn get_route(&self, identifier: &A::Identifier) -> Option<&[RouteLayer<A>]> {
+        self.routes.get(identifier).map(|v| v.as_slice())
+    }
+
+    /// Adds a follow-up computed from what this character actually receives from an action,
+    /// e.g. [`FollowUp::reflect`]. [`Target::Actor`](crate::Target::Actor) is the character
+    /// that used the action, and targets the action did not resolve are skipped.
+    pub fn add_incoming_follow_up(&mut self, follow_up: FollowUp<A>) {
+        self.incoming_follow_ups.push(follow_up);
+    }
+
+    pub fn incoming_follow_ups(&self) -> &[FollowUp<A>] {
+        &self.incoming_follow_ups
+    }
+}
+
+/// A layer such as a shield or barrier that absorbs negative `Add` changes aimed at another
+/// attribute. Each layer takes `absorb` times the remaining change, bounded by the remaining
+/// change and by its current value, and whatever is left over is applied to the original
+/// attribute.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteLayer<A: Attribute> {
+    identifier: A::Identifier,
+    absorb: A::Value,
+}
+
+impl<A: Attribute> RouteLayer<A> {
+    pub fn new(identifier: A::Identifier, absorb: A::Value) -> Self {
+        Self { identifier, absorb }
+    }
+
+    pub fn identifier(&self) -> &A::Identifier {
+        &self.identifier
+    }
+
+    pub fn absorb(&self) -> A::Value {
+        self.absorb
+    }
 }
 
 pub trait AttributeValue:
@@ -101,6 +235,8 @@ pub trait AttributeValue:
     + Sub<Output = Self>
     + Mul<Self, Output = Self>
     + Div<Self, Output = Self>
+    + PartialOrd
+    + Default
     + Debug
     + Copy
     + Clone
@@ -112,6 +248,8 @@ impl<
             + Sub<Output = T>
             + Mul<T, Output = T>
             + Div<T, Output = T>
+            + PartialOrd
+            + Default
             + Debug
             + Copy
             + Clone,
@@ -138,22 +276,41 @@ pub trait StringAttribute: Attribute<Identifier = String> {}
 // STATUS
 // ===============
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(serialize = "S: Serialize", deserialize = "S: Deserialize<'de>"))]
 pub struct StatusCollection<S: Status> {
+    #[serde(serialize_with = "crate::hash::sorted_set")]
     statuses: HashSet<S>,
 }
 
 impl<S: Status> StatusCollection<S> {
-    pub fn add(&mut self, status: S) {
-        self.statuses.insert(status);
+    /// Returns whether the status was newly added
+    pub fn add(&mut self, status: S) -> bool {
+        self.statuses.insert(status)
     }
 
-    pub fn remove(&mut self, status: &S) {
-        self.statuses.remove(status);
+    /// Returns whether the status was present
+    pub fn remove(&mut self, status: &S) -> bool {
+        self.statuses.remove(status)
     }
     pub fn contains(&self, status: &S) -> bool {
         self.statuses.contains(status)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &S> {
+        self.statuses.iter()
+    }
+
+    /// A hash of the statuses that does not depend on the order of the underlying set
+    pub fn state_hash(&self) -> u64 {
+        unordered_hash(self.statuses.iter().map(stable_hash))
+    }
 }
 
-pub trait Status: Debug + Clone + Default + Eq + PartialEq + Hash {}
+pub trait Status: Debug + Clone + Default + Eq + PartialEq + Hash {
+    /// Whether the status keeps a character from using actions with the given tags, e.g. a
+    /// silence blocking "spell" actions or a stun blocking every action
+    fn blocks(&self, _tags: &BTreeSet<String>) -> bool {
+        false
+    }
+}
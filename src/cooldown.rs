@@ -0,0 +1,150 @@
+use crate::hash::{stable_hash, unordered_hash};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How often an action can be used. Every use spends a charge, and a spent charge comes back
+/// once `turns` of the actor's turns have started.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Cooldown {
+    turns: u32,
+    charges: u32,
+}
+
+impl Cooldown {
+    /// A single charge recharging over `turns` turns, which is at least one
+    pub fn new(turns: u32) -> Self {
+        Self {
+            turns: turns.max(1),
+            charges: 1,
+        }
+    }
+
+    pub fn with_charges(mut self, charges: u32) -> Self {
+        self.charges = charges.max(1);
+        self
+    }
+
+    pub fn turns(&self) -> u32 {
+        self.turns
+    }
+
+    pub fn charges(&self) -> u32 {
+        self.charges
+    }
+}
+
+/// Charges left of a single action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct Charges {
+    cooldown: Cooldown,
+    available: u32,
+    /// Turns until the next charge comes back
+    remaining: u32,
+}
+
+/// Per-character cooldown tracking, keyed by [`Action::name`](crate::Action::name). Actions
+/// that were never used are not tracked and always ready.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Cooldowns {
+    #[serde(serialize_with = "crate::hash::sorted_map")]
+    actions: HashMap<String, Charges>,
+}
+
+impl Cooldowns {
+    /// Charges left of `action`, or `None` if it is not tracked
+    pub fn charges(&self, action: &str) -> Option<u32> {
+        self.actions.get(action).map(|c| c.available)
+    }
+
+    pub fn is_ready(&self, action: &str) -> bool {
+        self.turns_until_ready(action) == 0
+    }
+
+    /// Turns until `action` can be used again, `0` if it can be used now
+    pub fn turns_until_ready(&self, action: &str) -> u32 {
+        match self.actions.get(action) {
+            Some(c) if c.available == 0 => c.remaining,
+            _ => 0,
+        }
+    }
+
+    /// Spends a charge of `action`, returning `false` if none is left
+    pub fn spend(&mut self, action: &str, cooldown: Cooldown) -> bool {
+        let charges = self.actions.entry(action.to_string()).or_insert(Charges {
+            cooldown,
+            available: cooldown.charges,
+            remaining: 0,
+        });
+        if charges.available == 0 {
+            return false;
+        }
+        if charges.available == charges.cooldown.charges {
+            charges.remaining = charges.cooldown.turns;
+        }
+        charges.available -= 1;
+        true
+    }
+
+    /// Advances every cooldown by one turn
+    pub fn tick(&mut self) {
+        for charges in self.actions.values_mut() {
+            if charges.available == charges.cooldown.charges {
+                continue;
+            }
+            charges.remaining = charges.remaining.saturating_sub(1);
+            if charges.remaining == 0 {
+                charges.available += 1;
+                charges.remaining = charges.cooldown.turns;
+            }
+        }
+        self.actions.retain(|_, c| c.available < c.cooldown.charges);
+    }
+
+    /// A hash of every tracked action that does not depend on the order of the underlying map
+    pub fn state_hash(&self) -> u64 {
+        unordered_hash(self.actions.iter().map(|(name, c)| {
+            stable_hash(&(
+                name,
+                c.cooldown.turns,
+                c.cooldown.charges,
+                c.available,
+                c.remaining,
+            ))
+        }))
+    }
+
+    /// Makes `action` fully available again
+    pub fn reset(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charges() {
+        let mut cooldowns = Cooldowns::default();
+        let cooldown = Cooldown::new(2).with_charges(2);
+        assert!(cooldowns.spend("Dash", cooldown));
+        assert!(cooldowns.spend("Dash", cooldown));
+        assert!(!cooldowns.spend("Dash", cooldown));
+        assert_eq!(cooldowns.turns_until_ready("Dash"), 2);
+
+        cooldowns.tick();
+        assert_eq!(cooldowns.turns_until_ready("Dash"), 1);
+        cooldowns.tick();
+        assert_eq!(cooldowns.charges("Dash"), Some(1));
+        assert!(cooldowns.is_ready("Dash"));
+        cooldowns.tick();
+        cooldowns.tick();
+        assert_eq!(cooldowns.charges("Dash"), None);
+        assert!(cooldowns.is_ready("Fireball"));
+
+        let instant = Cooldown::new(0);
+        assert_eq!(instant.turns(), 1);
+        assert!(cooldowns.spend("Blink", instant));
+        assert!(!cooldowns.is_ready("Blink"));
+    }
+}
@@ -0,0 +1,1940 @@
+use crate::{
+    can_use, stable_hash, validate, Action, ActionError, ActionResult, Allegiance, Attribute,
+    Character, CharacterId, Command, Condition, Cooldowns, Delta, EncounterLog, EncounterOutcome,
+    EndCondition, Event, Factions, History, HistoryEntry, InnerAction, LogEntry, Modifier, Preview,
+    Reaction, ReplayError, ResolvedTargets, Rng, Roster, StableHasher, Status, Subject, Summon,
+    Target, Team, MAX_PREVIEW_OUTCOMES, PREVIEW_SAMPLES,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+pub const DEFAULT_MAX_REACTION_DEPTH: usize = 8;
+
+/// A group of characters acting on each other. Executing actions through an encounter applies
+/// modifiers, reports everything that happened as [`Event`]s and runs the [`Reaction`]s those
+/// events trigger.
+#[derive(Debug)]
+pub struct Encounter<A: Attribute, S: Status, M: Modifier<Attr = A>> {
+    characters: Roster<Character<A, S, M>>,
+    reactions: Vec<(Option<CharacterId>, Reaction<A, S>)>,
+    defeat_rule: Option<Condition<A, S>>,
+    current: Option<CharacterId>,
+    turn: usize,
+    max_reaction_depth: usize,
+    rng: Rng,
+    factions: Factions,
+    allegiance_statuses: Vec<(S, Allegiance)>,
+    end_conditions: Vec<EndCondition<A, S, M>>,
+    outcome: Option<EncounterOutcome>,
+    templates: HashMap<String, Character<A, S, M>>,
+    summons: Vec<SummonRecord>,
+    history: History<A, S>,
+    strict: bool,
+    log: Option<EncounterLog<A, S, M>>,
+}
+
+/// A hash of the full state of a single character, see [`Encounter::state_hash`]
+pub fn character_hash<A, S, M>(character: &Character<A, S, M>) -> u64
+where
+    A: Attribute,
+    S: Status,
+    M: Modifier<Attr = A>,
+{
+    let (base, attributes, statuses, incoming, outgoing) = character;
+    stable_hash(&(
+        base.team(),
+        base.is_defeated(),
+        base.cooldowns().state_hash(),
+        attributes.state_hash(),
+        statuses.state_hash(),
+        incoming.state_hash(),
+        outgoing.state_hash(),
+    ))
+}
+
+/// Everything about an encounter that changes while it runs: the characters, turn order, random
+/// state, factions, summons and outcome. Reactions, end conditions, templates, allegiance
+/// statuses and the defeat rule make up the setup of an encounter instead, which is built in
+/// code. See [`Encounter::snapshot`] and [`Encounter::restore`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Character<A, S, M>: Serialize",
+    deserialize = "Character<A, S, M>: Deserialize<'de>"
+))]
+pub struct EncounterState<A: Attribute, S: Status, M: Modifier<Attr = A>> {
+    characters: Roster<Character<A, S, M>>,
+    current: Option<CharacterId>,
+    turn: usize,
+    rng: Rng,
+    factions: Factions,
+    outcome: Option<EncounterOutcome>,
+    summons: Vec<SummonRecord>,
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> Clone for EncounterState<A, S, M> {
+    fn clone(&self) -> Self {
+        Self {
+            characters: self.characters.clone(),
+            current: self.current,
+            turn: self.turn,
+            rng: self.rng.clone(),
+            factions: self.factions.clone(),
+            outcome: self.outcome.clone(),
+            summons: self.summons.clone(),
+        }
+    }
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> EncounterState<A, S, M> {
+    pub fn characters(&self) -> &Roster<Character<A, S, M>> {
+        &self.characters
+    }
+
+    /// The hash an encounter in this state has, see [`Encounter::state_hash`]
+    pub fn state_hash(&self) -> u64 {
+        hash_state(
+            &self.characters,
+            self.current,
+            self.turn,
+            &self.rng,
+            &self.factions,
+            self.outcome.is_some(),
+            &self.summons,
+        )
+    }
+}
+
+fn hash_state<A, S, M>(
+    characters: &Roster<Character<A, S, M>>,
+    current: Option<CharacterId>,
+    turn: usize,
+    rng: &Rng,
+    factions: &Factions,
+    over: bool,
+    summons: &[SummonRecord],
+) -> u64
+where
+    A: Attribute,
+    S: Status,
+    M: Modifier<Attr = A>,
+{
+    let mut hasher = StableHasher::default();
+    turn.hash(&mut hasher);
+    current.hash(&mut hasher);
+    rng.hash(&mut hasher);
+    factions.state_hash().hash(&mut hasher);
+    over.hash(&mut hasher);
+    for (id, character) in characters.iter() {
+        id.hash(&mut hasher);
+        character_hash(character).hash(&mut hasher);
+    }
+    for s in summons {
+        (s.character, s.summoner, s.remaining, s.bound).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// State captured before an action is executed, see [`Encounter::rollback`]
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    cooldowns: Vec<(CharacterId, Cooldowns)>,
+    rng: Rng,
+    events: usize,
+}
+
+/// A character brought in by a [`Summon`] that may leave again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SummonRecord {
+    character: CharacterId,
+    summoner: CharacterId,
+    remaining: Option<u32>,
+    bound: bool,
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> Default for Encounter<A, S, M> {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> Encounter<A, S, M> {
+    pub fn new(characters: Vec<Character<A, S, M>>) -> Self {
+        Self {
+            characters: characters.into(),
+            reactions: vec![],
+            defeat_rule: None,
+            current: None,
+            turn: 0,
+            max_reaction_depth: DEFAULT_MAX_REACTION_DEPTH,
+            rng: Rng::default(),
+            factions: Factions::default(),
+            allegiance_statuses: vec![],
+            end_conditions: vec![],
+            outcome: None,
+            templates: HashMap::new(),
+            summons: vec![],
+            history: History::default(),
+            strict: false,
+            log: None,
+        }
+    }
+
+    pub fn add_character(&mut self, character: Character<A, S, M>) -> CharacterId {
+        self.characters.insert(character)
+    }
+
+    pub fn characters(&self) -> &Roster<Character<A, S, M>> {
+        &self.characters
+    }
+
+    pub fn character(&self, id: CharacterId) -> Option<&Character<A, S, M>> {
+        self.characters.get(id)
+    }
+
+    pub fn character_mut(&mut self, id: CharacterId) -> Option<&mut Character<A, S, M>> {
+        self.characters.get_mut(id)
+    }
+
+    /// Registers a reaction to events concerning any character
+    pub fn add_reaction(&mut self, reaction: Reaction<A, S>) {
+        self.reactions.push((None, reaction));
+    }
+
+    /// Registers a reaction to events concerning `character` only
+    pub fn add_character_reaction(&mut self, character: CharacterId, reaction: Reaction<A, S>) {
+        self.reactions.push((Some(character), reaction));
+    }
+
+    /// Characters meeting `rule`, with both subjects being the character itself, are out of
+    /// combat. They are skipped in turn order and by every target other than an explicitly
+    /// chosen one, and come back once they no longer meet the rule.
+    pub fn set_defeat_rule(&mut self, rule: Condition<A, S>) {
+        for (_, (base, attributes, statuses, ..)) in self.characters.iter_mut() {
+            let character = (&*attributes, &*statuses);
+            base.set_defeated(rule.evaluate(&character, &character));
+        }
+        self.defeat_rule = Some(rule);
+    }
+
+    /// A character whose `identifier` attribute is zero or below is defeated
+    pub fn set_defeat_attribute(&mut self, identifier: A::Identifier) {
+        self.set_defeat_rule(Condition::Not(Box::new(Condition::AttributeAbove(
+            Subject::Receiver,
+            identifier,
+            A::Value::default(),
+        ))));
+    }
+
+    /// Whether `character` is out of combat, which includes having left the encounter
+    pub fn is_defeated(&self, character: CharacterId) -> bool {
+        self.characters
+            .get(character)
+            .is_none_or(|c| c.0.is_defeated())
+    }
+
+    /// Re-evaluates the defeat rule for `character`, returning the resulting event if its
+    /// state changed
+    fn update_defeated(&mut self, character: CharacterId) -> Option<Event<A, S>> {
+        let rule = self.defeat_rule.as_ref()?;
+        let (base, attributes, statuses, ..) = self.characters.get_mut(character)?;
+        let defeated = rule.evaluate(&(&*attributes, &*statuses), &(&*attributes, &*statuses));
+        if defeated == base.is_defeated() {
+            return None;
+        }
+        base.set_defeated(defeated);
+        Some(if defeated {
+            Event::CharacterDefeated { character }
+        } else {
+            Event::CharacterRevived { character }
+        })
+    }
+
+    /// Whether `character` is still part of the encounter
+    pub fn is_present(&self, character: CharacterId) -> bool {
+        self.characters.contains(character)
+    }
+
+    /// Characters that are present and have not been defeated
+    pub fn survivors(&self) -> Vec<CharacterId> {
+        self.characters
+            .iter()
+            .filter(|(_, c)| !c.0.is_defeated())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Registers a character that [`Summon`]s can create copies of
+    pub fn add_template<T: ToString>(&mut self, name: T, character: Character<A, S, M>) {
+        self.templates.insert(name.to_string(), character);
+    }
+
+    /// Removes `character` from the encounter along with every summon bound to it. Its id and
+    /// the reactions registered on it are no longer valid afterwards.
+    pub fn despawn(&mut self, character: CharacterId) -> Vec<Event<A, S>> {
+        self.record(LogEntry::Despawn(character));
+        self.history.clear();
+        let mut events = vec![];
+        self.despawn_at_depth(character, 0, &mut events);
+        events
+    }
+
+    fn despawn_at_depth(
+        &mut self,
+        character: CharacterId,
+        depth: usize,
+        events: &mut Vec<Event<A, S>>,
+    ) {
+        if self.characters.remove(character).is_none() {
+            return;
+        }
+        self.summons.retain(|s| s.character != character);
+        self.reactions
+            .retain(|(owner, _)| *owner != Some(character));
+        self.dispatch(
+            Event::CharacterDespawned { character },
+            character,
+            depth,
+            events,
+        );
+    }
+
+    /// Adds a copy of the summon's template to `summoner`'s team
+    fn summon(
+        &mut self,
+        summoner: CharacterId,
+        summon: &Summon,
+        depth: usize,
+        events: &mut Vec<Event<A, S>>,
+    ) {
+        let (Some(mut character), Some(summoner_character)) = (
+            self.templates.get(summon.template()).cloned(),
+            self.characters.get(summoner),
+        ) else {
+            return;
+        };
+        character.0.set_team(summoner_character.0.team());
+        let index = self.add_character(character);
+        self.summons.push(SummonRecord {
+            character: index,
+            summoner,
+            remaining: summon.duration(),
+            bound: summon.is_bound(),
+        });
+        self.dispatch(
+            Event::CharacterSummoned {
+                character: index,
+                summoner,
+            },
+            summoner,
+            depth,
+            events,
+        );
+    }
+
+    /// Despawns summons whose time is up at the start of their summoner's turn, or whose
+    /// summoner is gone
+    fn update_summons(&mut self, event: &Event<A, S>, depth: usize, events: &mut Vec<Event<A, S>>) {
+        let expired: Vec<CharacterId> = match event {
+            Event::TurnStarted { character } => self
+                .summons
+                .iter_mut()
+                .filter(|s| s.summoner == *character)
+                .filter_map(|s| {
+                    let remaining = s.remaining.as_mut()?;
+                    *remaining = remaining.saturating_sub(1);
+                    (*remaining == 0).then_some(s.character)
+                })
+                .collect(),
+            Event::CharacterDefeated { character } | Event::CharacterDespawned { character } => {
+                self.summons
+                    .iter()
+                    .filter(|s| s.bound && s.summoner == *character)
+                    .map(|s| s.character)
+                    .collect()
+            }
+            _ => vec![],
+        };
+        for character in expired {
+            self.despawn_at_depth(character, depth, events);
+        }
+    }
+
+    /// Conditions are checked in the order they were added, after every executed action and
+    /// before every turn
+    pub fn add_end_condition(&mut self, condition: EndCondition<A, S, M>) {
+        self.end_conditions.push(condition);
+    }
+
+    /// How the encounter ended, or `None` while it is still running
+    pub fn outcome(&self) -> Option<&EncounterOutcome> {
+        self.outcome.as_ref()
+    }
+
+    fn update_outcome(&mut self, turn_over: bool) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.outcome = self
+            .end_conditions
+            .iter()
+            .find_map(|c| c.evaluate(self, turn_over))
+            .map(|winner| EncounterOutcome::new(winner, self.turn, self.survivors()));
+    }
+
+    /// In strict mode every action, including reactions, is checked with [`validate()`] against
+    /// its actor before it is executed and rejected with [`ActionError::Invalid`] if it has
+    /// any problems. Changes to attributes a receiver does not have fail with
+    /// [`ActionError::MissingReceiverAttribute`] and roll the action back, instead of being
+    /// skipped.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Limits how deeply reactions may trigger further reactions
+    pub fn set_max_reaction_depth(&mut self, depth: usize) {
+        self.max_reaction_depth = depth;
+    }
+
+    /// Seeds the random number generator used for random choices
+    pub fn set_seed(&mut self, seed: u64) {
+        self.record(LogEntry::Seed(seed));
+        self.history.clear();
+        self.rng = Rng::new(seed);
+    }
+
+    /// The random number generator used for random choices and random targets
+    pub fn rng(&self) -> &Rng {
+        &self.rng
+    }
+
+    /// Number of turns started so far
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    /// The character whose turn it is
+    pub fn current(&self) -> Option<CharacterId> {
+        self.current
+    }
+
+    /// Hands the turn to the next character in roster order that is not defeated and advances
+    /// its cooldowns, unless the encounter is over
+    pub fn next_turn(&mut self) -> Vec<Event<A, S>> {
+        self.record(LogEntry::NextTurn);
+        self.history.clear();
+        let mut events = vec![];
+        self.update_outcome(true);
+        if self.outcome.is_some() {
+            return events;
+        }
+        let active = self.survivors();
+        let Some(character) = active
+            .iter()
+            .find(|i| self.current.is_none_or(|c| i.index() > c.index()))
+            .or(active.first())
+            .copied()
+        else {
+            return events;
+        };
+        self.current = Some(character);
+        self.turn += 1;
+        self.characters[character].0.cooldowns_mut().tick();
+        self.dispatch(Event::TurnStarted { character }, character, 0, &mut events);
+        events
+    }
+
+    pub fn factions(&self) -> &Factions {
+        &self.factions
+    }
+
+    pub fn set_relation(&mut self, a: Team, b: Team, hostile: bool) {
+        self.factions.set_relation(a, b, hostile);
+    }
+
+    /// While a character has `status`, e.g. charm or confusion, it fights for `allegiance`
+    /// instead of its own team. The first matching status registered wins.
+    pub fn add_allegiance_status(&mut self, status: S, allegiance: Allegiance) {
+        self.allegiance_statuses.push((status, allegiance));
+    }
+
+    /// The side `character` currently fights for, if it is part of the encounter
+    pub fn allegiance(&self, character: CharacterId) -> Option<Allegiance> {
+        let (base, _, statuses, ..) = self.characters.get(character)?;
+        let allegiance = self
+            .allegiance_statuses
+            .iter()
+            .find(|(s, _)| statuses.contains(s))
+            .map_or(Allegiance::Team(base.team()), |(_, a)| *a);
+        Some(allegiance)
+    }
+
+    /// Whether `a` and `b` fight each other. Characters that are not part of the encounter
+    /// fight no one.
+    pub fn is_hostile(&self, a: CharacterId, b: CharacterId) -> bool {
+        match (self.allegiance(a), self.allegiance(b)) {
+            (Some(x), Some(y)) => a != b && self.factions.are_hostile(x, y),
+            _ => false,
+        }
+    }
+
+    /// Resolves every target referenced by `action` against the roster. [`Target::Target`]
+    /// resolves to `chosen`, every other selector skips defeated characters. [`Target::Actor`]
+    /// is always resolved, as the actor's outgoing modifiers apply to every part of the action.
+    /// Nothing is resolved for an actor that is not part of the encounter.
+    ///
+    /// Random selectors draw from `rng` rather than from the encounter, so previewing targets,
+    /// e.g. with a clone of [`Encounter::rng`], does not change the outcome of later actions.
+    pub fn resolve_targets(
+        &self,
+        rng: &mut Rng,
+        actor: CharacterId,
+        action: &InnerAction<A, S>,
+        chosen: &[CharacterId],
+    ) -> ResolvedTargets<A::Identifier> {
+        if !self.characters.contains(actor) {
+            return ResolvedTargets::new();
+        }
+        let mut resolved = ResolvedTargets::from([(Target::Actor, vec![actor])]);
+        for target in action.targets() {
+            let active = self.survivors();
+            let enemies = active
+                .iter()
+                .copied()
+                .filter(|i| self.is_hostile(actor, *i));
+            let characters = match &target {
+                Target::Actor => vec![actor],
+                Target::Target => chosen.to_vec(),
+                Target::AllEnemies => enemies.collect(),
+                Target::AllAllies => active
+                    .iter()
+                    .copied()
+                    .filter(|i| !self.is_hostile(actor, *i))
+                    .collect(),
+                Target::Everyone => active.clone(),
+                Target::RandomEnemy(n) => {
+                    let mut pool: Vec<CharacterId> = enemies.collect();
+                    let mut picked = vec![];
+                    while picked.len() < *n as usize && !pool.is_empty() {
+                        let i = rng.below(pool.len() as u64) as usize;
+                        picked.push(pool.remove(i));
+                    }
+                    picked
+                }
+                Target::LowestAttribute(identifier) => enemies
+                    .filter_map(|i| {
+                        self.characters[i]
+                            .1
+                            .get_attribute_value(identifier)
+                            .map(|v| (i, v))
+                    })
+                    .reduce(|a, b| if b.1 < a.1 { b } else { a })
+                    .map(|(i, _)| i)
+                    .into_iter()
+                    .collect(),
+            };
+            resolved.insert(target, characters);
+        }
+        resolved
+    }
+
+    /// Executes `action` on the `chosen` targets along with every reaction it triggers,
+    /// returning all events in order. The actor pays the costs of the action first, and nothing
+    /// happens if it cannot use the action, see [`can_use`], or is no longer present. Chosen
+    /// targets that are no longer present are skipped. If applying the action fails, its costs,
+    /// cooldown and the reactions triggered so far are rolled back along with it.
+    pub fn execute(
+        &mut self,
+        actor: CharacterId,
+        action: &Action<A, S>,
+        chosen: &[CharacterId],
+    ) -> Result<Vec<Event<A, S>>, ActionError<A, S>> {
+        let Checkpoint { cooldowns, rng, .. } = self.checkpoint(&[]);
+        let mut events = vec![];
+        self.execute_at_depth(actor, action, chosen, 0, &mut events)?;
+        self.update_outcome(false);
+
+        let result = events.iter().filter_map(|e| e.delta()).collect::<Vec<_>>();
+        let cooldowns = cooldowns
+            .into_iter()
+            .filter_map(|(id, before)| {
+                let after = self.characters.get(id)?.0.cooldowns();
+                (*after != before).then(|| (id, before, after.clone()))
+            })
+            .collect();
+        let roster_changed = events.iter().any(|e| {
+            matches!(
+                e,
+                Event::CharacterSummoned { .. } | Event::CharacterDespawned { .. }
+            )
+        });
+        if roster_changed {
+            self.history.clear();
+        } else {
+            self.history.push(HistoryEntry::new(
+                actor,
+                action.name().to_string(),
+                result.into(),
+                cooldowns,
+                (rng, self.rng.clone()),
+            ));
+        }
+        self.record(LogEntry::Execute(Command::new(
+            actor,
+            action.name(),
+            chosen.to_vec(),
+        )));
+        Ok(events)
+    }
+
+    /// Every action executed through [`Encounter::execute`] that can be undone or redone
+    pub fn history(&self) -> &History<A, S> {
+        &self.history
+    }
+
+    /// Rewinds the last executed action, including its costs, cooldowns, the random state and
+    /// the reactions it triggered, and re-evaluates defeat and the outcome. Returns whether there
+    /// was an action to undo.
+    ///
+    /// Undoing restores the state from before the action, so only actions executed since the
+    /// encounter last changed otherwise can be undone: starting a turn, despawning a character
+    /// or seeding clears the history, and so does an action that summoned a character or made
+    /// one leave, as the roster cannot be rewound. Changes made through [`Encounter::character_mut`] are not
+    /// tracked and may be overwritten.
+    pub fn undo(&mut self) -> bool {
+        let Some(entry) = self.history.undo() else {
+            return false;
+        };
+        let entry = entry.clone();
+        self.record(LogEntry::Undo);
+        let (mut attributes, mut statuses) =
+            self.characters.map_mut(|(_, a, s, ..)| (a, s)).unzip();
+        entry.result().revert(&mut attributes, &mut statuses);
+        for (id, before, _) in entry.cooldowns() {
+            if let Some(c) = self.characters.get_mut(*id) {
+                *c.0.cooldowns_mut() = before.clone();
+            }
+        }
+        self.rng = entry.rng().0.clone();
+        self.rewound(&entry);
+        true
+    }
+
+    /// Executes the last undone action again with the same outcome, returning whether there was
+    /// one
+    pub fn redo(&mut self) -> bool {
+        let Some(entry) = self.history.redo() else {
+            return false;
+        };
+        let entry = entry.clone();
+        self.record(LogEntry::Redo);
+        let (mut attributes, mut statuses) =
+            self.characters.map_mut(|(_, a, s, ..)| (a, s)).unzip();
+        entry.result().reapply(&mut attributes, &mut statuses);
+        for (id, _, after) in entry.cooldowns() {
+            if let Some(c) = self.characters.get_mut(*id) {
+                *c.0.cooldowns_mut() = after.clone();
+            }
+        }
+        self.rng = entry.rng().1.clone();
+        self.rewound(&entry);
+        true
+    }
+
+    /// Starts a new [`EncounterLog`] from a [`snapshot`](Encounter::snapshot) of the current
+    /// state. Executed actions, turns, undos, redos, despawns and seeds are recorded from then
+    /// on. Any other change to the encounter, such as adding a character, cannot be replayed and
+    /// shows up as a desync at the next checkpoint.
+    pub fn start_recording(&mut self) {
+        self.log = Some(EncounterLog::new(self.snapshot()));
+    }
+
+    /// The log recorded since [`Encounter::start_recording`]
+    pub fn log(&self) -> Option<&EncounterLog<A, S, M>> {
+        self.log.as_ref()
+    }
+
+    /// Stops recording and returns the log
+    pub fn take_log(&mut self) -> Option<EncounterLog<A, S, M>> {
+        self.log.take()
+    }
+
+    /// Records the current state hash, which [`Encounter::replay`] verifies
+    pub fn record_checkpoint(&mut self) {
+        let hash = self.state_hash();
+        self.record(LogEntry::Checkpoint(hash));
+    }
+
+    fn record(&mut self, entry: LogEntry) {
+        if let Some(log) = &mut self.log {
+            log.push(entry);
+        }
+    }
+
+    /// A cheap hash of the full state of the encounter, for comparing the states of peers in
+    /// lockstep multiplayer or verifying replays: the attribute, status and modifier
+    /// collections, cooldowns, team and defeat state of every character along with factions,
+    /// summons, turn order and the random state. It does not depend on the iteration order of
+    /// hash maps and sets, and is the same on every platform, but only for builds with the same
+    /// toolchain, see [`StableHasher`].
+    pub fn state_hash(&self) -> u64 {
+        hash_state(
+            &self.characters,
+            self.current,
+            self.turn,
+            &self.rng,
+            &self.factions,
+            self.outcome.is_some(),
+            &self.summons,
+        )
+    }
+
+    /// The current state of the encounter, which can be saved, e.g. with
+    /// [`Migrations::save`](crate::Migrations::save), and restored later
+    pub fn snapshot(&self) -> EncounterState<A, S, M> {
+        EncounterState {
+            characters: self.characters.clone(),
+            current: self.current,
+            turn: self.turn,
+            rng: self.rng.clone(),
+            factions: self.factions.clone(),
+            outcome: self.outcome.clone(),
+            summons: self.summons.clone(),
+        }
+    }
+
+    /// Replaces the state of the encounter with `state`, keeping its setup. Ids refer to the
+    /// characters of `state` afterwards. The history is cleared and recording stops, as neither
+    /// applies to the restored state.
+    pub fn restore(&mut self, state: EncounterState<A, S, M>) {
+        let EncounterState {
+            characters,
+            current,
+            turn,
+            rng,
+            factions,
+            outcome,
+            summons,
+        } = state;
+        self.characters = characters;
+        self.current = current;
+        self.turn = turn;
+        self.rng = rng;
+        self.factions = factions;
+        self.outcome = outcome;
+        self.summons = summons;
+        self.history.clear();
+        self.log = None;
+    }
+
+    /// Restores the state `log` was started from, see [`Encounter::restore`], and runs every
+    /// entry of it, looking up executed actions by name in `actions`. The encounter has to have the
+    /// same setup, such as reactions and templates, as the one that recorded the log. Stops at
+    /// the first checkpoint the state does not match. Like restoring, replaying stops
+    /// recording, so the replayed entries are not recorded again.
+    pub fn replay(
+        &mut self,
+        log: &EncounterLog<A, S, M>,
+        actions: &[Action<A, S>],
+    ) -> Result<(), ReplayError<A, S>> {
+        self.restore(log.initial().clone());
+        for (entry, e) in log.entries().iter().enumerate() {
+            match e {
+                LogEntry::Execute(command) => {
+                    let action = actions
+                        .iter()
+                        .find(|a| a.name() == command.action())
+                        .ok_or_else(|| ReplayError::UnknownAction {
+                            entry,
+                            action: command.action().to_string(),
+                        })?;
+                    self.execute(command.actor(), action, command.targets())
+                        .map_err(|error| ReplayError::Action { entry, error })?;
+                }
+                LogEntry::NextTurn => {
+                    self.next_turn();
+                }
+                LogEntry::Undo => {
+                    self.undo();
+                }
+                LogEntry::Redo => {
+                    self.redo();
+                }
+                LogEntry::Despawn(character) => {
+                    self.despawn(*character);
+                }
+                LogEntry::Seed(seed) => self.set_seed(*seed),
+                LogEntry::Checkpoint(expected) => {
+                    let actual = self.state_hash();
+                    if actual != *expected {
+                        return Err(ReplayError::Desync {
+                            entry,
+                            expected: *expected,
+                            actual,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Brings defeat and the outcome in line with the state after an undo or redo
+    fn rewound(&mut self, entry: &HistoryEntry<A, S>) {
+        for character in entry.result().characters() {
+            self.update_defeated(character);
+        }
+        self.outcome = None;
+        self.update_outcome(false);
+    }
+
+    /// The state an action that fails part way is rolled back to
+    fn checkpoint(&self, events: &[Event<A, S>]) -> Checkpoint {
+        Checkpoint {
+            cooldowns: self
+                .characters
+                .iter()
+                .map(|(id, c)| (id, c.0.cooldowns().clone()))
+                .collect(),
+            rng: self.rng.clone(),
+            events: events.len(),
+        }
+    }
+
+    /// Reverts every change reported by the events since `checkpoint` and removes the
+    /// characters summoned since. Characters that left in the meantime do not come back.
+    fn rollback(&mut self, checkpoint: Checkpoint, events: &mut Vec<Event<A, S>>) {
+        let undone = events.split_off(checkpoint.events);
+        let result: ActionResult<A, S> = undone
+            .iter()
+            .filter_map(|e| e.delta())
+            .collect::<Vec<_>>()
+            .into();
+        let (mut attributes, mut statuses) =
+            self.characters.map_mut(|(_, a, s, ..)| (a, s)).unzip();
+        result.revert(&mut attributes, &mut statuses);
+        for event in &undone {
+            if let Event::CharacterSummoned { character, .. } = event {
+                self.characters.remove(*character);
+                self.summons.retain(|s| s.character != *character);
+                self.reactions
+                    .retain(|(owner, _)| *owner != Some(*character));
+            }
+        }
+        for (id, cooldowns) in checkpoint.cooldowns {
+            if let Some(c) = self.characters.get_mut(id) {
+                *c.0.cooldowns_mut() = cooldowns;
+            }
+        }
+        for character in result.characters() {
+            self.update_defeated(character);
+        }
+        self.rng = checkpoint.rng;
+    }
+
+    fn execute_at_depth(
+        &mut self,
+        actor: CharacterId,
+        action: &Action<A, S>,
+        chosen: &[CharacterId],
+        depth: usize,
+        events: &mut Vec<Event<A, S>>,
+    ) -> Result<(), ActionError<A, S>> {
+        let checkpoint = self.checkpoint(events);
+        self.check_usable(actor, action)?;
+        let (base, attributes, statuses, _, outgoing) = &mut self.characters[actor];
+        if let Some(cooldown) = action.cooldown() {
+            // A cooldown without charges, e.g. from a hand-written save, is never ready
+            if !base.cooldowns_mut().spend(action.name(), cooldown) {
+                return Err(ActionError::OnCooldown {
+                    action: action.name().to_string(),
+                    turns: base.cooldowns().turns_until_ready(action.name()).max(1),
+                });
+            }
+        }
+        let paid = match action.pay_costs(attributes, statuses, outgoing) {
+            Ok(paid) => paid,
+            Err(e) => {
+                self.rollback(checkpoint, events);
+                return Err(e);
+            }
+        };
+        let started = Event::ActionStarted {
+            actor,
+            action: action.name().to_string(),
+        };
+        self.dispatch(started, actor, depth, events);
+        for delta in paid {
+            if let Some(event) = Event::from_delta(actor, Delta::Attribute(delta)) {
+                self.dispatch(event, actor, depth, events);
+            }
+        }
+
+        let mut rng = std::mem::take(&mut self.rng);
+        let targets = self.resolve_targets(&mut rng, actor, action.inner(), chosen);
+        self.rng = rng;
+        let deltas = match Self::apply(
+            &mut self.characters,
+            &mut self.rng,
+            action,
+            &targets,
+            self.strict,
+        ) {
+            Ok(deltas) => deltas,
+            Err(e) => {
+                self.rollback(checkpoint, events);
+                return Err(e);
+            }
+        };
+        for (character, delta) in deltas {
+            match delta {
+                Delta::Summon(summon) => self.summon(actor, &summon, depth, events),
+                delta => {
+                    if let Some(event) = Event::from_delta(character, delta) {
+                        self.dispatch(event, actor, depth, events);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `actor` can use `action` right now, see [`can_use`], and in strict mode whether
+    /// the action is valid
+    fn check_usable(
+        &self,
+        actor: CharacterId,
+        action: &Action<A, S>,
+    ) -> Result<(), ActionError<A, S>> {
+        let character = self
+            .characters
+            .get(actor)
+            .ok_or(ActionError::MissingCharacter(actor))?;
+        if self.strict {
+            let errors = validate(action, character);
+            if !errors.is_empty() {
+                return Err(ActionError::Invalid(errors));
+            }
+        }
+        can_use(action, character)
+    }
+
+    /// Predicts the effect of executing `action` without changing the encounter, failing the
+    /// same way [`Encounter::execute`] would if the action cannot be used. Reactions are not
+    /// included. Every combination of random choices and random targets is walked through with
+    /// its probability, which makes the preview exact, unless there are more than
+    /// [`MAX_PREVIEW_OUTCOMES`]. Those actions are sampled [`PREVIEW_SAMPLES`] times with the
+    /// current random state instead, see [`Preview::is_exact`].
+    pub fn preview(
+        &self,
+        actor: CharacterId,
+        action: &Action<A, S>,
+        chosen: &[CharacterId],
+    ) -> Result<Preview<A, S>, ActionError<A, S>>
+    where
+        A::Value: Into<f64>,
+    {
+        self.check_usable(actor, action)?;
+        let run = |rng: &mut Rng| -> Result<ActionResult<A, S>, ActionError<A, S>> {
+            let mut characters = self.characters.clone();
+            let (_, attributes, statuses, _, outgoing) = &mut characters[actor];
+            let mut result = ActionResult::new();
+            for delta in action.pay_costs(attributes, statuses, outgoing)? {
+                result.push(actor, Delta::Attribute(delta));
+            }
+            let targets = self.resolve_targets(rng, actor, action.inner(), chosen);
+            result.extend(Self::apply(
+                &mut characters,
+                rng,
+                action,
+                &targets,
+                self.strict,
+            )?);
+            Ok(result)
+        };
+
+        let mut rng = Rng::enumerate();
+        let mut outcomes = vec![];
+        while outcomes.len() < MAX_PREVIEW_OUTCOMES {
+            outcomes.push((run(&mut rng)?, rng.probability()));
+            if !rng.advance() {
+                return Ok(Preview::from_outcomes(&outcomes, true));
+            }
+        }
+        let mut rng = self.rng.clone();
+        let samples = (0..PREVIEW_SAMPLES)
+            .map(|_| Ok((run(&mut rng)?, 1. / PREVIEW_SAMPLES as f64)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Preview::from_outcomes(&samples, false))
+    }
+
+    /// Applies `action` with the modifiers of every actor and receiver involved, see
+    /// [`InnerAction::apply_strict`] for `strict`
+    fn apply(
+        characters: &mut Roster<Character<A, S, M>>,
+        rng: &mut Rng,
+        action: &Action<A, S>,
+        targets: &ResolvedTargets<A::Identifier>,
+        strict: bool,
+    ) -> Result<ActionResult<A, S>, ActionError<A, S>> {
+        let (collections, modifiers) = characters
+            .map_mut(|(_, a, s, i, o)| ((a, s), (&*i, &*o)))
+            .unzip();
+        let (mut attributes, mut statuses) = collections.unzip();
+        let (incoming, outgoing) = modifiers.unzip();
+        action.inner().apply_prepared(
+            &mut attributes,
+            &mut statuses,
+            targets,
+            rng,
+            strict,
+            &|a, actor, receiver, attributes, statuses| {
+                Cow::Owned(a.apply_modifiers(
+                    &(&*attributes[actor], &*statuses[actor], outgoing[actor]),
+                    &(
+                        &*attributes[receiver],
+                        &*statuses[receiver],
+                        incoming[receiver],
+                    ),
+                    action,
+                ))
+            },
+        )
+    }
+
+    /// Records `event` and runs the reactions it triggers. `source` is the character whose
+    /// action caused the event.
+    fn dispatch(
+        &mut self,
+        event: Event<A, S>,
+        source: CharacterId,
+        depth: usize,
+        events: &mut Vec<Event<A, S>>,
+    ) {
+        let character = event.character();
+        let dispatched = event.clone();
+        let changed = matches!(
+            event,
+            Event::AttributeChanged { .. }
+                | Event::StatusAdded { .. }
+                | Event::StatusRemoved { .. }
+        );
+
+        let triggered: Vec<_> = self
+            .reactions
+            .iter()
+            .filter(|(owner, r)| {
+                owner.is_none_or(|o| o == character) && r.trigger().matches(&event)
+            })
+            .map(|(owner, r)| (owner.unwrap_or(character), r.action().clone()))
+            .collect();
+        events.push(event);
+
+        if depth < self.max_reaction_depth {
+            for (owner, action) in triggered {
+                // Reactions the owner cannot pay for are skipped
+                let _ = self.execute_at_depth(owner, &action, &[source], depth + 1, events);
+            }
+        }
+        if let Some(event) = changed.then(|| self.update_defeated(character)).flatten() {
+            self.dispatch(event, source, depth, events);
+        }
+        self.update_summons(&dispatched, depth, events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use crate::{
+        serde_json, ActionElement, AttributeChange, AttributeChangeType, CharacterBase, Cooldown,
+        CustomAction, InnerAction, Migrations, SimpleAction, StatusChange, Trigger,
+    };
+
+    fn damage(name: &str, amount: f64) -> Action<Attr, Buff> {
+        Action::new(
+            name.to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![AttributeChange::new(Id::Hp, -amount).into()],
+            )),
+        )
+    }
+
+    #[test]
+    fn test_thorns() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.add_character_reaction(
+            ids[1],
+            Reaction::new(Trigger::AttributeDecreased(Id::Hp), damage("Thorns", 5.)),
+        );
+        let events = encounter
+            .execute(ids[0], &damage("Hit", 20.), &[ids[1]])
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::ActionStarted {
+                    actor: ids[0],
+                    action: "Hit".to_string()
+                },
+                Event::AttributeChanged {
+                    character: ids[1],
+                    identifier: Id::Hp,
+                    old: 100.,
+                    new: 80.
+                },
+                Event::ActionStarted {
+                    actor: ids[1],
+                    action: "Thorns".to_string()
+                },
+                Event::AttributeChanged {
+                    character: ids[0],
+                    identifier: Id::Hp,
+                    old: 100.,
+                    new: 95.
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outgoing_modifiers() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter
+            .character_mut(ids[0])
+            .unwrap()
+            .4
+            .add_by_action_name("Hit", Scale(2.));
+        encounter
+            .execute(ids[0], &damage("Hit", 10.), &[ids[1]])
+            .unwrap();
+        assert_eq!(
+            encounter.characters()[ids[1]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(80.)
+        );
+    }
+
+    #[test]
+    fn test_target_selectors() {
+        let mut encounter = Encounter::new(vec![
+            character_in(0, 100.),
+            character_in(1, 100.),
+            character_in(1, 60.),
+            character_in(2, 80.),
+        ]);
+        let ids = encounter.characters().ids();
+        encounter
+            .character_mut(ids[2])
+            .unwrap()
+            .3
+            .add_by_attribute_name(Id::Hp, Scale(0.5));
+        let hit = |target| {
+            Action::new(
+                "Hit".to_string(),
+                InnerAction::Simple(SimpleAction::new(
+                    target,
+                    vec![AttributeChange::new(Id::Hp, -10.).into()],
+                )),
+            )
+        };
+        let hp = |encounter: &Encounter<Attr, Buff, Scale>| {
+            encounter
+                .characters()
+                .iter()
+                .map(|(_, c)| c.1.get_attribute_value(&Id::Hp).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        encounter
+            .execute(ids[0], &hit(Target::AllEnemies), &[])
+            .unwrap();
+        assert_eq!(hp(&encounter), vec![100., 90., 55., 70.]);
+        encounter
+            .execute(ids[0], &hit(Target::LowestAttribute(Id::Hp)), &[])
+            .unwrap();
+        assert_eq!(hp(&encounter), vec![100., 90., 50., 70.]);
+        encounter
+            .execute(ids[0], &hit(Target::AllAllies), &[])
+            .unwrap();
+        assert_eq!(hp(&encounter), vec![90., 90., 50., 70.]);
+
+        let events = encounter
+            .execute(ids[0], &hit(Target::RandomEnemy(2)), &[])
+            .unwrap();
+        let mut hit_characters: Vec<_> = events[1..].iter().map(|e| e.character()).collect();
+        hit_characters.dedup();
+        assert_eq!(hit_characters.len(), 2);
+        assert!(!hit_characters.contains(&ids[0]));
+
+        // Resolving targets outside of execute leaves the encounter's random state alone
+        let hash = encounter.state_hash();
+        let random = hit(Target::RandomEnemy(2));
+        let mut rng = encounter.rng().clone();
+        let targets = encounter.resolve_targets(&mut rng, ids[0], random.inner(), &[]);
+        assert_eq!(targets[&Target::RandomEnemy(2)].len(), 2);
+        assert_eq!(encounter.state_hash(), hash);
+
+        // Characters that left resolve nothing and fight no one
+        encounter.despawn(ids[0]);
+        let targets = encounter.resolve_targets(&mut rng, ids[0], random.inner(), &[]);
+        assert!(targets.is_empty());
+        assert_eq!(encounter.allegiance(ids[0]), None);
+        assert!(!encounter.is_hostile(ids[0], ids[1]));
+    }
+
+    #[test]
+    fn test_allegiance() {
+        let mut encounter = Encounter::new(vec![
+            character_in(0, 100.),
+            character_in(0, 100.),
+            character_in(1, 100.),
+            character_in(2, 100.),
+        ]);
+        let ids = encounter.characters().ids();
+        encounter.set_relation(Team(1), Team(2), false);
+        encounter.add_allegiance_status(Buff::Charmed, Allegiance::Team(Team(0)));
+        encounter.add_allegiance_status(Buff::Confused, Allegiance::Hostile);
+        assert!(!encounter.is_hostile(ids[0], ids[1]));
+        assert!(encounter.is_hostile(ids[0], ids[2]));
+        assert!(!encounter.is_hostile(ids[2], ids[3]));
+
+        encounter
+            .character_mut(ids[2])
+            .unwrap()
+            .2
+            .add(Buff::Charmed);
+        assert!(!encounter.is_hostile(ids[0], ids[2]));
+        assert!(encounter.is_hostile(ids[2], ids[3]));
+
+        encounter
+            .character_mut(ids[1])
+            .unwrap()
+            .2
+            .add(Buff::Confused);
+        assert!(encounter.is_hostile(ids[0], ids[1]));
+        assert!(!encounter.is_hostile(ids[1], ids[1]));
+    }
+
+    #[test]
+    fn test_end_conditions() {
+        let mut encounter = Encounter::new(vec![
+            character_in(0, 100.),
+            character_in(0, 100.),
+            character_in(1, 20.),
+        ]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_attribute(Id::Hp);
+        encounter.add_end_condition(EndCondition::TurnLimit(2));
+        encounter.add_end_condition(EndCondition::LastTeamStanding);
+
+        encounter.next_turn();
+        encounter
+            .execute(ids[0], &damage("Hit", 10.), &[ids[2]])
+            .unwrap();
+        assert!(encounter.outcome().is_none());
+        encounter.next_turn();
+        encounter
+            .execute(ids[1], &damage("Hit", 10.), &[ids[2]])
+            .unwrap();
+        assert_eq!(
+            encounter.outcome(),
+            Some(&EncounterOutcome::new(
+                Some(Team(0)),
+                2,
+                vec![ids[0], ids[1]]
+            ))
+        );
+        assert!(encounter.next_turn().is_empty());
+    }
+
+    #[test]
+    fn test_charmed_survivor() {
+        let mut encounter = Encounter::new(vec![
+            character_in(0, 100.),
+            character_in(1, 10.),
+            character_in(1, 100.),
+        ]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_attribute(Id::Hp);
+        encounter.add_allegiance_status(Buff::Charmed, Allegiance::Team(Team(0)));
+        encounter.add_end_condition(EndCondition::LastTeamStanding);
+        encounter
+            .character_mut(ids[2])
+            .unwrap()
+            .2
+            .add(Buff::Charmed);
+
+        encounter
+            .execute(ids[0], &damage("Hit", 10.), &[ids[1]])
+            .unwrap();
+        assert_eq!(
+            encounter.outcome(),
+            Some(&EncounterOutcome::new(
+                Some(Team(0)),
+                0,
+                vec![ids[0], ids[2]]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_turn_limit() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.add_end_condition(EndCondition::TurnLimit(2));
+        encounter.add_end_condition(EndCondition::Custom(|e| {
+            e.is_defeated(e.characters().ids()[1])
+                .then_some(Some(Team(0)))
+        }));
+
+        assert_eq!(encounter.next_turn().len(), 1);
+        assert_eq!(encounter.next_turn().len(), 1);
+        assert!(encounter.outcome().is_none());
+        assert!(encounter.next_turn().is_empty());
+        assert_eq!(
+            encounter.outcome(),
+            Some(&EncounterOutcome::new(None, 2, ids))
+        );
+    }
+
+    #[test]
+    fn test_defeat_and_revive() {
+        let mut encounter = Encounter::new(vec![
+            character_in(0, 100.),
+            character_in(1, 10.),
+            character_in(1, 100.),
+        ]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_rule(Condition::Any(vec![
+            Condition::AttributeBelow(Subject::Receiver, Id::Hp, 1.),
+            Condition::HasStatus(Subject::Receiver, Buff::Frozen),
+        ]));
+        let hit_all = Action::new(
+            "Quake".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::AllEnemies,
+                vec![AttributeChange::new(Id::Hp, -10.).into()],
+            )),
+        );
+
+        let events = encounter.execute(ids[0], &hit_all, &[]).unwrap();
+        assert_eq!(events[2], Event::CharacterDefeated { character: ids[1] });
+        assert_eq!(encounter.survivors(), vec![ids[0], ids[2]]);
+        encounter.execute(ids[0], &hit_all, &[]).unwrap();
+        assert_eq!(
+            encounter.characters()[ids[1]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(0.)
+        );
+
+        let freeze = Action::new(
+            "Freeze".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![ActionElement::StatusChange(StatusChange::Add(Buff::Frozen))],
+            )),
+        );
+        encounter.execute(ids[0], &freeze, &[ids[2]]).unwrap();
+        assert!(encounter.is_defeated(ids[2]));
+        encounter.next_turn();
+        assert_eq!(encounter.next_turn().len(), 1);
+        assert_eq!(encounter.current(), Some(ids[0]));
+
+        let events = encounter
+            .execute(ids[0], &damage("Revive", -50.), &[ids[1]])
+            .unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&Event::CharacterRevived { character: ids[1] })
+        );
+        assert_eq!(encounter.survivors(), vec![ids[0], ids[1]]);
+    }
+
+    #[test]
+    fn test_reaction_depth() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_attribute(Id::Hp);
+        encounter.set_max_reaction_depth(4);
+        for id in &ids {
+            encounter.add_character_reaction(
+                *id,
+                Reaction::new(Trigger::AttributeDecreased(Id::Hp), damage("Thorns", 30.)),
+            );
+        }
+        let events = encounter
+            .execute(ids[0], &damage("Hit", 40.), &[ids[1]])
+            .unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&Event::CharacterDefeated { character: ids[1] })
+        );
+        assert_eq!(
+            encounter.characters()[ids[0]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(40.)
+        );
+        assert_eq!(
+            encounter.characters()[ids[1]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(0.)
+        );
+    }
+
+    #[test]
+    fn test_summons() {
+        let summon = |duration, bound| {
+            let mut summon = Summon::new("Wolf");
+            if let Some(d) = duration {
+                summon = summon.with_duration(d);
+            }
+            if bound {
+                summon = summon.bound_to_summoner();
+            }
+            Action::new(
+                "Summon".to_string(),
+                InnerAction::Simple(SimpleAction::new(
+                    Target::Actor,
+                    vec![ActionElement::Summon(summon)],
+                )),
+            )
+        };
+        let summoned = |events: &[Event<Attr, Buff>]| {
+            events.iter().find_map(|e| match e {
+                Event::CharacterSummoned { character, .. } => Some(*character),
+                _ => None,
+            })
+        };
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_attribute(Id::Hp);
+        encounter.add_template("Wolf", character(20.));
+
+        let events = encounter
+            .execute(ids[0], &summon(Some(2), false), &[])
+            .unwrap();
+        let wolf = summoned(&events).unwrap();
+        assert_eq!(encounter.characters()[wolf].0.team(), Team(0));
+        // The roster cannot be rewound, so summoning cannot be undone and repeated
+        assert!(!encounter.undo());
+        assert_eq!(encounter.characters().len(), 3);
+        assert!(encounter.is_hostile(wolf, ids[1]));
+
+        // The summon acts in turn order and leaves when its summoner starts its second turn
+        let turns: Vec<_> = (0..3)
+            .map(|_| encounter.next_turn()[0].character())
+            .collect();
+        assert_eq!(turns, vec![ids[0], ids[1], wolf]);
+        let events = encounter.next_turn();
+        assert!(events.contains(&Event::CharacterDespawned { character: wolf }));
+        assert!(!encounter.is_present(wolf));
+        assert_eq!(encounter.next_turn()[0].character(), ids[1]);
+
+        // Bound summons leave together with their summoner. The new summon takes the free slot,
+        // but the old id does not refer to it.
+        let events = encounter.execute(ids[1], &summon(None, true), &[]).unwrap();
+        let bound = summoned(&events).unwrap();
+        assert_eq!(bound.index(), wolf.index());
+        assert!(encounter.character(wolf).is_none());
+        assert_eq!(
+            encounter.execute(wolf, &damage("Hit", 10.), &[ids[0]]),
+            Err(ActionError::MissingCharacter(wolf))
+        );
+
+        let events = encounter
+            .execute(ids[0], &damage("Hit", 100.), &[ids[1]])
+            .unwrap();
+        assert!(events.contains(&Event::CharacterDespawned { character: bound }));
+        assert_eq!(encounter.survivors(), vec![ids[0]]);
+        assert!(!encounter.undo());
+    }
+
+    #[test]
+    fn test_summons_with_reactions() {
+        let summon = |summon: Summon| {
+            Action::new(
+                "Summon".to_string(),
+                InnerAction::Simple(SimpleAction::new(
+                    Target::Actor,
+                    vec![ActionElement::Summon(summon)],
+                )),
+            )
+        };
+        let regenerate = Action::new(
+            "Regenerate".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Actor,
+                vec![AttributeChange::new(Id::Mana, 1.).into()],
+            )),
+        );
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_attribute(Id::Hp);
+        encounter.add_template("Wolf", character(20.));
+        encounter.add_reaction(Reaction::new(Trigger::TurnStarted, regenerate));
+        encounter.add_character_reaction(
+            ids[1],
+            Reaction::new(Trigger::CharacterDefeated, damage("Curse", 5.)),
+        );
+
+        let events = encounter
+            .execute(ids[0], &summon(Summon::new("Wolf").with_duration(1)), &[])
+            .unwrap();
+        let Some(Event::CharacterSummoned {
+            character: wolf, ..
+        }) = events.last()
+        else {
+            unreachable!()
+        };
+        let events = encounter.next_turn();
+        assert!(events.contains(&Event::CharacterDespawned { character: *wolf }));
+
+        let events = encounter
+            .execute(
+                ids[1],
+                &summon(Summon::new("Wolf").bound_to_summoner()),
+                &[],
+            )
+            .unwrap();
+        let Some(Event::CharacterSummoned {
+            character: bound, ..
+        }) = events.last()
+        else {
+            unreachable!()
+        };
+        let events = encounter
+            .execute(ids[0], &damage("Hit", 100.), &[ids[1]])
+            .unwrap();
+        assert!(events.contains(&Event::CharacterDespawned { character: *bound }));
+    }
+
+    #[test]
+    fn test_costs() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        let fireball = damage("Fireball", 20.).with_cost(AttributeChange::new(Id::Mana, -30.));
+        let events = encounter.execute(ids[0], &fireball, &[ids[1]]).unwrap();
+        assert_eq!(
+            events[1],
+            Event::AttributeChanged {
+                character: ids[0],
+                identifier: Id::Mana,
+                old: 50.,
+                new: 20.
+            }
+        );
+
+        // Nothing is paid or dealt if any cost cannot be paid
+        let expensive = fireball
+            .clone()
+            .with_cost(AttributeChange::new(Id::Hp, -10.));
+        assert_eq!(
+            encounter.execute(ids[0], &expensive, &[ids[1]]),
+            Err(ActionError::InsufficientAttribute {
+                identifier: Id::Mana,
+                required: 30.,
+                available: 20.
+            })
+        );
+        assert_eq!(
+            encounter.characters()[ids[0]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(100.)
+        );
+        assert_eq!(
+            encounter.characters()[ids[1]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(80.)
+        );
+
+        // Cost modifiers reduce costs, damage modifiers leave them alone
+        let outgoing = &mut encounter.character_mut(ids[0]).unwrap().4;
+        outgoing.add_cost_by_action_name("Fireball", Scale(0.5));
+        outgoing.add_by_action_name("Fireball", Scale(2.));
+        encounter.execute(ids[0], &fireball, &[ids[1]]).unwrap();
+        assert_eq!(
+            encounter.characters()[ids[0]]
+                .1
+                .get_attribute_value(&Id::Mana),
+            Some(5.)
+        );
+        assert_eq!(
+            encounter.characters()[ids[1]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(40.)
+        );
+    }
+
+    #[test]
+    fn test_cooldowns() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        let smite = damage("Smite", 10.).with_cooldown(Cooldown::new(2));
+
+        encounter.next_turn();
+        encounter.execute(ids[0], &smite, &[ids[1]]).unwrap();
+        assert_eq!(
+            encounter.execute(ids[0], &smite, &[ids[1]]),
+            Err(ActionError::OnCooldown {
+                action: "Smite".to_string(),
+                turns: 2
+            })
+        );
+        encounter.next_turn();
+        encounter.next_turn();
+        let cooldowns = encounter.characters()[ids[0]].0.cooldowns();
+        assert_eq!(cooldowns.turns_until_ready("Smite"), 1);
+        encounter.next_turn();
+        encounter.next_turn();
+        encounter.execute(ids[0], &smite, &[ids[1]]).unwrap();
+
+        let cooldown = serde_json::from_str(r#"{"turns":1,"charges":0}"#).unwrap();
+        let broken = damage("Broken", 10.).with_cooldown(cooldown);
+        assert_eq!(
+            encounter.execute(ids[0], &broken, &[ids[1]]),
+            Err(ActionError::OnCooldown {
+                action: "Broken".to_string(),
+                turns: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter
+            .character_mut(ids[1])
+            .unwrap()
+            .3
+            .add_by_attribute_name(Id::Hp, Scale(0.5));
+        let fireball = damage("Fireball", 40.).with_cost(AttributeChange::new(Id::Mana, -10.));
+
+        let preview = encounter.preview(ids[0], &fireball, &[ids[1]]).unwrap();
+        assert_eq!(preview.outcomes(), 1);
+        let hp = preview
+            .character(ids[1])
+            .unwrap()
+            .attribute(&Id::Hp)
+            .unwrap();
+        assert_eq!((hp.min(), hp.max(), hp.expected()), (-20., -20., -20.));
+        let mana = preview.character(ids[0]).unwrap().attribute(&Id::Mana);
+        assert_eq!(mana.unwrap().expected(), -10.);
+        assert_eq!(
+            encounter.characters()[ids[1]]
+                .1
+                .get_attribute_value(&Id::Hp),
+            Some(100.)
+        );
+
+        let freeze = InnerAction::Simple(SimpleAction::new(
+            Target::Target,
+            vec![ActionElement::StatusChange(StatusChange::Add(Buff::Frozen))],
+        ));
+        let gamble = Action::new(
+            "Gamble".to_string(),
+            InnerAction::RandomChoice(vec![(99, damage("", 20.).inner().clone()), (1, freeze)]),
+        );
+        let preview = encounter.preview(ids[0], &gamble, &[ids[1]]).unwrap();
+        assert!(preview.is_exact());
+        assert_eq!(preview.outcomes(), 2);
+        let target = preview.character(ids[1]).unwrap();
+        let hp = target.attribute(&Id::Hp).unwrap();
+        assert_eq!((hp.min(), hp.max()), (-10., 0.));
+        assert!((hp.expected() + 9.9).abs() < 1e-9);
+        let frozen = &target.statuses()[0];
+        assert!(frozen.is_added() && (frozen.chance() - 0.01).abs() < 1e-9);
+
+        // Previews fail like executing would
+        let expensive = damage("Meteor", 10.).with_cost(AttributeChange::new(Id::Mana, -80.));
+        assert_eq!(
+            encounter.preview(ids[0], &expensive, &[ids[1]]),
+            Err(ActionError::InsufficientAttribute {
+                identifier: Id::Mana,
+                required: 80.,
+                available: 50.
+            })
+        );
+        encounter.set_strict(true);
+        let typo = damage("Typo", 10.)
+            .with_cost(AttributeChange::new(Id::Mana, 0.).with_op(AttributeChangeType::Set));
+        assert!(matches!(
+            encounter.preview(ids[0], &typo, &[ids[1]]),
+            Err(ActionError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.set_defeat_attribute(Id::Hp);
+        let execute = Action::new(
+            "Execute".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![
+                    AttributeChange::new(Id::Hp, 0.)
+                        .with_op(AttributeChangeType::Mul)
+                        .into(),
+                    ActionElement::StatusChange(StatusChange::Add(Buff::Frozen)),
+                ],
+            )),
+        )
+        .with_cost(AttributeChange::new(Id::Mana, -10.))
+        .with_cooldown(Cooldown::new(3));
+        let state = |encounter: &Encounter<Attr, Buff, Scale>| {
+            let (_, actor, ..) = &encounter.characters()[ids[0]];
+            let (_, target, statuses, ..) = &encounter.characters()[ids[1]];
+            (
+                actor.get_attribute_value(&Id::Mana).unwrap(),
+                target.get_attribute_value(&Id::Hp).unwrap(),
+                statuses.contains(&Buff::Frozen),
+                encounter.is_defeated(ids[1]),
+            )
+        };
+
+        encounter.execute(ids[0], &execute, &[ids[1]]).unwrap();
+        assert_eq!(state(&encounter), (40., 0., true, true));
+        assert!(encounter.undo());
+        assert_eq!(state(&encounter), (50., 100., false, false));
+        assert!(encounter.characters()[ids[0]]
+            .0
+            .cooldowns()
+            .is_ready("Execute"));
+        assert!(!encounter.undo());
+
+        assert!(encounter.redo());
+        assert_eq!(state(&encounter), (40., 0., true, true));
+        assert!(!encounter.characters()[ids[0]]
+            .0
+            .cooldowns()
+            .is_ready("Execute"));
+
+        encounter.undo();
+        encounter
+            .execute(ids[0], &damage("Hit", 10.), &[ids[1]])
+            .unwrap();
+        assert!(!encounter.history().can_redo());
+        assert_eq!(encounter.history().done().len(), 1);
+
+        // Undoing restores the random state, and turns cannot be undone
+        let roll = InnerAction::RandomChoice(
+            (1..=4)
+                .map(|i| (1, damage("", i as f64).inner().clone()))
+                .collect(),
+        );
+        let volley = Action::new("Volley".to_string(), InnerAction::Sequence(vec![roll; 8]));
+        let events = encounter.execute(ids[0], &volley, &[ids[1]]).unwrap();
+        encounter.undo();
+        assert_eq!(
+            encounter.execute(ids[0], &volley, &[ids[1]]).unwrap(),
+            events
+        );
+        encounter.next_turn();
+        assert!(!encounter.undo());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let setup = || {
+            let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+            encounter.set_defeat_attribute(Id::Hp);
+            encounter.add_template("Wolf", character(20.));
+            encounter
+        };
+        let summon = Action::new(
+            "Summon".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Actor,
+                vec![ActionElement::Summon(Summon::new("Wolf").with_duration(1))],
+            )),
+        )
+        .with_cooldown(Cooldown::new(3));
+        let roll = InnerAction::RandomChoice(
+            (1..=4)
+                .map(|i| (1, damage("", i as f64).inner().clone()))
+                .collect(),
+        );
+        let volley = Action::new("Volley".to_string(), InnerAction::Sequence(vec![roll; 8]));
+
+        let mut encounter = setup();
+        let ids = encounter.characters().ids();
+        encounter.set_seed(7);
+        encounter.set_relation(Team(0), Team(1), false);
+        encounter.next_turn();
+        encounter.execute(ids[0], &summon, &[]).unwrap();
+        encounter.character_mut(ids[1]).unwrap().2.add(Buff::Wet);
+
+        let migrations = Migrations::new();
+        let saved = migrations.save(&encounter.snapshot()).unwrap();
+        let state: EncounterState<Attr, Buff, Scale> = migrations.load(&saved).unwrap();
+        assert_eq!(state.state_hash(), encounter.state_hash());
+
+        let mut restored = setup();
+        restored.restore(state);
+        assert_eq!(restored.state_hash(), encounter.state_hash());
+        assert_eq!(restored.turn(), 1);
+        assert!(!restored.is_hostile(ids[0], ids[1]));
+        assert_eq!(
+            restored.characters()[ids[0]]
+                .0
+                .cooldowns()
+                .turns_until_ready("Summon"),
+            3
+        );
+        assert_eq!(
+            restored.execute(ids[0], &volley, &[ids[1]]).unwrap(),
+            encounter.execute(ids[0], &volley, &[ids[1]]).unwrap()
+        );
+        // The summon still leaves when its summoner starts its next turn
+        let turns = |encounter: &mut Encounter<Attr, Buff, Scale>| {
+            (0..3).map(|_| encounter.next_turn()).collect::<Vec<_>>()
+        };
+        assert_eq!(turns(&mut restored), turns(&mut encounter));
+        assert_eq!(restored.characters().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_action_rolls_back() {
+        let mut target = CharacterBase::new("target");
+        target.1.add_attribute(Id::Hp, Attr(100.));
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), target]);
+        let ids = encounter.characters().ids();
+        // Only strict encounters fail on receivers without an attribute
+        encounter.set_strict(true);
+        let drain = Action::new(
+            "Drain".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![
+                    AttributeChange::new(Id::Hp, -10.).into(),
+                    AttributeChange::new(Id::Mana, -10.).into(),
+                ],
+            )),
+        )
+        .with_cost(AttributeChange::new(Id::Mana, -20.))
+        .with_cooldown(Cooldown::new(2));
+
+        assert_eq!(
+            encounter.execute(ids[0], &drain, &[ids[1]]),
+            Err(ActionError::MissingReceiverAttribute {
+                character: ids[1],
+                identifier: Id::Mana
+            })
+        );
+        let (base, attributes, ..) = &encounter.characters()[ids[0]];
+        assert_eq!(attributes.get_attribute_value(&Id::Mana), Some(50.));
+        assert!(base.cooldowns().is_ready("Drain"));
+        let target = &encounter.characters()[ids[1]].1;
+        assert_eq!(target.get_attribute_value(&Id::Hp), Some(100.));
+        assert!(!encounter.history().can_undo());
+
+        // Custom actions fail instead of panicking, after earlier parts were applied
+        #[derive(Debug, Clone)]
+        struct Gambit;
+        impl CustomAction for Gambit {
+            fn name(&self) -> Option<&String> {
+                None
+            }
+        }
+        encounter.set_strict(false);
+        let gambit = Action::new(
+            "Gambit".to_string(),
+            InnerAction::Sequence(vec![
+                damage("", 10.).inner().clone(),
+                InnerAction::Custom(Box::new(Gambit)),
+            ]),
+        );
+        assert_eq!(
+            encounter.preview(ids[0], &gambit, &[ids[1]]).err(),
+            Some(ActionError::CustomAction)
+        );
+        assert_eq!(
+            encounter.execute(ids[0], &gambit, &[ids[1]]),
+            Err(ActionError::CustomAction)
+        );
+        let target = &encounter.characters()[ids[1]].1;
+        assert_eq!(target.get_attribute_value(&Id::Hp), Some(100.));
+    }
+
+    #[test]
+    fn test_replay() {
+        let setup = || {
+            let mut encounter = Encounter::new(vec![
+                character_in(0, 100.),
+                character_in(1, 100.),
+                character_in(1, 100.),
+            ]);
+            encounter.set_defeat_attribute(Id::Hp);
+            encounter
+        };
+        let volley = Action::new(
+            "Volley".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::RandomEnemy(1),
+                vec![AttributeChange::new(Id::Hp, -15.).into()],
+            )),
+        );
+        let actions = [volley.clone(), damage("Hit", 10.)];
+
+        let mut encounter = setup();
+        let ids = encounter.characters().ids();
+        encounter.start_recording();
+        encounter.set_seed(3);
+        for _ in 0..4 {
+            encounter.next_turn();
+            let actor = encounter.current().unwrap();
+            encounter.execute(actor, &volley, &[]).unwrap();
+        }
+        encounter
+            .execute(ids[1], &damage("Hit", 10.), &[ids[0]])
+            .unwrap();
+        encounter.undo();
+        encounter.record_checkpoint();
+        let log = encounter.take_log().unwrap();
+        let log: EncounterLog<Attr, Buff, Scale> =
+            serde_json::from_str(&serde_json::to_string(&log).unwrap()).unwrap();
+
+        // The log carries its initial state, so changes made before replaying do not matter
+        let mut replayed = setup();
+        replayed.character_mut(ids[0]).unwrap().2.add(Buff::Wet);
+        replayed.start_recording();
+        assert_eq!(replayed.replay(&log, &actions), Ok(()));
+        assert_eq!(replayed.state_hash(), encounter.state_hash());
+        assert!(replayed.log().is_none());
+
+        let mut diverged = setup();
+        diverged.start_recording();
+        assert!(matches!(
+            diverged.replay(&log, &actions[1..]),
+            Err(ReplayError::UnknownAction { entry: 2, .. })
+        ));
+        let mut diverged = setup();
+        diverged.add_reaction(Reaction::new(
+            Trigger::AttributeDecreased(Id::Hp),
+            damage("Thorns", 5.),
+        ));
+        assert!(matches!(
+            diverged.replay(&log, &actions),
+            Err(ReplayError::Desync { entry: 11, .. })
+        ));
+    }
+}
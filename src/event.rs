@@ -0,0 +1,181 @@
+use crate::{Action, Attribute, AttributeDelta, CharacterId, Delta, Status};
+use serde::{Deserialize, Serialize};
+
+// ===============
+// Events
+// ===============
+
+/// Something that happened during an encounter. Every event concerns a single character, the
+/// id of which is returned by [`Event::character`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Event<A: Attribute, S: Status> {
+    ActionStarted {
+        actor: CharacterId,
+        action: String,
+    },
+    AttributeChanged {
+        character: CharacterId,
+        identifier: A::Identifier,
+        old: A::Value,
+        new: A::Value,
+    },
+    StatusAdded {
+        character: CharacterId,
+        status: S,
+    },
+    StatusRemoved {
+        character: CharacterId,
+        status: S,
+    },
+    TurnStarted {
+        character: CharacterId,
+    },
+    CharacterDefeated {
+        character: CharacterId,
+    },
+    CharacterRevived {
+        character: CharacterId,
+    },
+    CharacterSummoned {
+        character: CharacterId,
+        summoner: CharacterId,
+    },
+    CharacterDespawned {
+        character: CharacterId,
+    },
+}
+
+impl<A: Attribute, S: Status> Event<A, S> {
+    /// The event reporting `delta`, if it was a change to an existing character
+    pub(crate) fn from_delta(character: CharacterId, delta: Delta<A, S>) -> Option<Self> {
+        match delta {
+            Delta::Attribute(a) => Some(Self::AttributeChanged {
+                character,
+                identifier: a.identifier().clone(),
+                old: a.old(),
+                new: a.new_value(),
+            }),
+            Delta::StatusAdded(status) => Some(Self::StatusAdded { character, status }),
+            Delta::StatusRemoved(status) => Some(Self::StatusRemoved { character, status }),
+            Delta::Summon(_) => None,
+        }
+    }
+
+    /// The delta reported by the event, if it changed an attribute or a status
+    pub fn delta(&self) -> Option<(CharacterId, Delta<A, S>)> {
+        match self {
+            Self::AttributeChanged {
+                character,
+                identifier,
+                old,
+                new,
+            } => Some((
+                *character,
+                Delta::Attribute(AttributeDelta::new(identifier.clone(), *old, *new)),
+            )),
+            Self::StatusAdded { character, status } => {
+                Some((*character, Delta::StatusAdded(status.clone())))
+            }
+            Self::StatusRemoved { character, status } => {
+                Some((*character, Delta::StatusRemoved(status.clone())))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn character(&self) -> CharacterId {
+        match self {
+            Self::ActionStarted { actor, .. } => *actor,
+            Self::AttributeChanged { character, .. }
+            | Self::StatusAdded { character, .. }
+            | Self::StatusRemoved { character, .. }
+            | Self::TurnStarted { character }
+            | Self::CharacterDefeated { character }
+            | Self::CharacterRevived { character }
+            | Self::CharacterSummoned { character, .. }
+            | Self::CharacterDespawned { character } => *character,
+        }
+    }
+}
+
+// ===============
+// Reactions
+// ===============
+
+/// Which events a [`Reaction`] responds to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Trigger<A: Attribute, S: Status> {
+    ActionStarted,
+    AttributeChanged(A::Identifier),
+    AttributeIncreased(A::Identifier),
+    AttributeDecreased(A::Identifier),
+    StatusAdded(S),
+    StatusRemoved(S),
+    TurnStarted,
+    CharacterDefeated,
+    CharacterRevived,
+    CharacterSummoned,
+    CharacterDespawned,
+}
+
+impl<A: Attribute, S: Status> Trigger<A, S> {
+    pub fn matches(&self, event: &Event<A, S>) -> bool {
+        match (self, event) {
+            (Self::ActionStarted, Event::ActionStarted { .. })
+            | (Self::TurnStarted, Event::TurnStarted { .. })
+            | (Self::CharacterDefeated, Event::CharacterDefeated { .. })
+            | (Self::CharacterRevived, Event::CharacterRevived { .. })
+            | (Self::CharacterSummoned, Event::CharacterSummoned { .. })
+            | (Self::CharacterDespawned, Event::CharacterDespawned { .. }) => true,
+            (Self::AttributeChanged(i), Event::AttributeChanged { identifier, .. }) => {
+                i == identifier
+            }
+            (
+                Self::AttributeIncreased(i),
+                Event::AttributeChanged {
+                    identifier,
+                    old,
+                    new,
+                    ..
+                },
+            ) => i == identifier && new > old,
+            (
+                Self::AttributeDecreased(i),
+                Event::AttributeChanged {
+                    identifier,
+                    old,
+                    new,
+                    ..
+                },
+            ) => i == identifier && new < old,
+            (Self::StatusAdded(s), Event::StatusAdded { status, .. })
+            | (Self::StatusRemoved(s), Event::StatusRemoved { status, .. }) => s == status,
+            _ => false,
+        }
+    }
+}
+
+/// An action performed in response to an event, e.g. a counterattack or thorns.
+///
+/// When the reaction fires, [`Target::Actor`](crate::Target::Actor) is the character it is
+/// registered on (or the character the event concerns, for encounter-wide reactions) and
+/// [`Target::Target`](crate::Target::Target) is the actor that caused the event.
+#[derive(Debug, Clone)]
+pub struct Reaction<A: Attribute, S: Status> {
+    trigger: Trigger<A, S>,
+    action: Action<A, S>,
+}
+
+impl<A: Attribute, S: Status> Reaction<A, S> {
+    pub fn new(trigger: Trigger<A, S>, action: Action<A, S>) -> Self {
+        Self { trigger, action }
+    }
+
+    pub fn trigger(&self) -> &Trigger<A, S> {
+        &self.trigger
+    }
+
+    pub fn action(&self) -> &Action<A, S> {
+        &self.action
+    }
+}
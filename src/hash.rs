@@ -0,0 +1,171 @@
+use serde::{Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+/// A 64 bit FNV-1a hasher. Unlike the hashers of the standard library its output is specified,
+/// and integers are always written as little endian with `usize` and `isize` widened to 64 bits,
+/// so the same value hashes the same on every platform.
+///
+/// The bytes it is fed come from the [`Hash`] implementations of the hashed types, which the
+/// standard library does not keep stable across compiler versions, and values without a `Hash`
+/// implementation, such as attribute values and modifiers, are hashed through their `Debug`
+/// output, which can change as well. Hashes are therefore only comparable between builds
+/// with the same toolchain and dependencies. Games that keep hashes around, e.g. in replay logs,
+/// should pin their toolchain and expect a [`ReplayError::Desync`](crate::ReplayError::Desync)
+/// after upgrading it.
+#[derive(Debug, Clone)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// The [`StableHasher`] hash of `value`
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes a value that does not implement [`Hash`], such as an `f64`, through its `Debug`
+/// output, which is exact for numbers but not guaranteed to stay the same between Rust
+/// versions, see [`StableHasher`]
+pub(crate) fn debug_hash<T: Debug + ?Sized>(value: &T) -> u64 {
+    stable_hash(&format!("{value:?}"))
+}
+
+/// Combines the hashes of the items of an unordered collection, e.g. a `HashSet`, so that the
+/// result does not depend on the order they are iterated in
+pub(crate) fn unordered_hash(hashes: impl IntoIterator<Item = u64>) -> u64 {
+    let mut hashes: Vec<u64> = hashes.into_iter().collect();
+    hashes.sort_unstable();
+    stable_hash(&hashes)
+}
+
+/// Serializes a `HashMap` with its entries ordered by the [`stable_hash`] of their keys, so the
+/// same map is always written the same way. Used with `#[serde(serialize_with)]`.
+pub(crate) fn sorted_map<K, V, Ser>(
+    map: &HashMap<K, V>,
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error>
+where
+    K: Hash + Serialize,
+    V: Serialize,
+    Ser: Serializer,
+{
+    let mut entries: Vec<(u64, &K, &V)> = map.iter().map(|(k, v)| (stable_hash(k), k, v)).collect();
+    entries.sort_unstable_by_key(|(h, ..)| *h);
+    serializer.collect_map(entries.into_iter().map(|(_, k, v)| (k, v)))
+}
+
+/// Serializes a `HashSet` ordered by the [`stable_hash`] of its items, see [`sorted_map`]
+pub(crate) fn sorted_set<T, Ser>(set: &HashSet<T>, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+where
+    T: Hash + Serialize,
+    Ser: Serializer,
+{
+    let mut items: Vec<(u64, &T)> = set.iter().map(|t| (stable_hash(t), t)).collect();
+    items.sort_unstable_by_key(|(h, _)| *h);
+    serializer.collect_seq(items.into_iter().map(|(_, t)| t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modifier::IncomingModifierCollection;
+    use crate::testing::*;
+    use crate::{AttributeCollection, StatusCollection};
+
+    #[test]
+    fn test_stable_hash() {
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash(&1usize), stable_hash(&1u64));
+    }
+
+    #[test]
+    fn test_order_independence() {
+        let ids = [Id::Hp, Id::MaxHp, Id::Mana, Id::Barrier];
+        let build = |order: &mut dyn Iterator<Item = &Id>| {
+            let mut attributes = AttributeCollection::<Attr>::new();
+            let mut statuses = StatusCollection::default();
+            let mut modifiers = IncomingModifierCollection::default();
+            for id in order {
+                attributes.add_attribute(*id, Attr(*id as u8 as f64));
+                statuses.add([Buff::Wet, Buff::Frozen, Buff::Stunned, Buff::Charmed][*id as usize]);
+                modifiers.add_by_attribute_name(*id, Scale(0.5));
+                modifiers.add_by_change_tag(format!("{id:?}"), Scale(2.));
+            }
+            (
+                attributes.state_hash(),
+                statuses.state_hash(),
+                modifiers.state_hash(),
+            )
+        };
+        let forward = build(&mut ids.iter());
+        assert_eq!(forward, build(&mut ids.iter().rev()));
+
+        let mut attributes = AttributeCollection::<Attr>::new();
+        attributes.add_attribute(Id::Hp, Attr(1.));
+        let before = attributes.state_hash();
+        attributes.set_attribute_value(&Id::Hp, 1.5);
+        assert_ne!(before, attributes.state_hash());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{ActionResult, Attribute, CharacterId, Cooldowns, Rng, Status};
+
+/// An action executed through an [`Encounter`](crate::Encounter), along with everything needed
+/// to rewind it
+#[derive(Debug, Clone)]
+pub struct HistoryEntry<A: Attribute, S: Status> {
+    actor: CharacterId,
+    action: String,
+    result: ActionResult<A, S>,
+    cooldowns: Vec<(CharacterId, Cooldowns, Cooldowns)>,
+    rng: (Rng, Rng),
+}
+
+impl<A: Attribute, S: Status> HistoryEntry<A, S> {
+    pub(crate) fn new(
+        actor: CharacterId,
+        action: String,
+        result: ActionResult<A, S>,
+        cooldowns: Vec<(CharacterId, Cooldowns, Cooldowns)>,
+        rng: (Rng, Rng),
+    ) -> Self {
+        Self {
+            actor,
+            action,
+            result,
+            cooldowns,
+            rng,
+        }
+    }
+
+    pub fn actor(&self) -> CharacterId {
+        self.actor
+    }
+
+    pub fn action(&self) -> &str {
+        self.action.as_str()
+    }
+
+    /// Every change of the action, including its costs and the reactions it triggered
+    pub fn result(&self) -> &ActionResult<A, S> {
+        &self.result
+    }
+
+    /// The cooldowns of each character whose cooldowns the action changed, from before and
+    /// after it was executed
+    pub(crate) fn cooldowns(&self) -> &[(CharacterId, Cooldowns, Cooldowns)] {
+        &self.cooldowns
+    }
+
+    /// The random state from before and after the action was executed
+    pub(crate) fn rng(&self) -> &(Rng, Rng) {
+        &self.rng
+    }
+}
+
+/// Executed actions that can be undone and redone, see [`Encounter::undo`](crate::Encounter::undo).
+/// Only actions executed since the last change to the encounter other than an action, such as
+/// the start of a turn, are kept.
+#[derive(Debug, Clone)]
+pub struct History<A: Attribute, S: Status> {
+    done: Vec<HistoryEntry<A, S>>,
+    undone: Vec<HistoryEntry<A, S>>,
+}
+
+impl<A: Attribute, S: Status> Default for History<A, S> {
+    fn default() -> Self {
+        Self {
+            done: vec![],
+            undone: vec![],
+        }
+    }
+}
+
+impl<A: Attribute, S: Status> History<A, S> {
+    /// Records a newly executed action, which drops every undone action
+    pub(crate) fn push(&mut self, entry: HistoryEntry<A, S>) {
+        self.done.push(entry);
+        self.undone.clear();
+    }
+
+    pub(crate) fn undo(&mut self) -> Option<&HistoryEntry<A, S>> {
+        let entry = self.done.pop()?;
+        self.undone.push(entry);
+        self.undone.last()
+    }
+
+    pub(crate) fn redo(&mut self) -> Option<&HistoryEntry<A, S>> {
+        let entry = self.undone.pop()?;
+        self.done.push(entry);
+        self.done.last()
+    }
+
+    /// Actions that were executed and not undone, oldest first
+    pub fn done(&self) -> &[HistoryEntry<A, S>] {
+        &self.done
+    }
+
+    /// Actions that were undone, most recently undone last
+    pub fn undone(&self) -> &[HistoryEntry<A, S>] {
+        &self.undone
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+}
@@ -1,11 +1,39 @@
 mod action;
 pub mod character;
+pub mod cooldown;
+pub mod encounter;
+pub mod event;
+pub mod hash;
+pub mod history;
+pub mod outcome;
+pub mod preview;
+pub mod random;
+pub mod replay;
+pub mod roster;
+pub mod save;
+pub mod team;
+#[cfg(test)]
+mod testing;
 
 pub use action::*;
+#[cfg(feature = "binary")]
+pub use bincode;
 pub use character::*;
+pub use cooldown::*;
+pub use encounter::*;
+pub use event::*;
+pub use hash::{stable_hash, StableHasher};
+pub use history::*;
+pub use outcome::*;
+pub use preview::*;
+pub use random::*;
+pub use replay::*;
+pub use roster::*;
+pub use save::*;
 pub use serde;
 pub use serde_json;
 use std::fmt::{Display, Formatter};
+pub use team::*;
 use thiserror::Error;
 pub use typetag;
 
@@ -17,8 +45,6 @@ pub enum Error {
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            _ => write!(f, "{:?}", self),
-        }
+        write!(f, "{:?}", self)
     }
 }
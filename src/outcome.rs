@@ -0,0 +1,104 @@
+use crate::{Allegiance, Attribute, CharacterId, Encounter, Modifier, Status, Team};
+use serde::{Deserialize, Serialize};
+
+/// Decides the winner of an encounter once it is over. Returns `None` while the encounter is
+/// still running and `Some(None)` for a draw.
+pub type EndCheck<A, S, M> = fn(&Encounter<A, S, M>) -> Option<Option<Team>>;
+
+/// When an [`Encounter`] is over. Characters count as alive until they are defeated, see
+/// [`Encounter::is_defeated`].
+#[derive(Debug)]
+pub enum EndCondition<A: Attribute, S: Status, M: Modifier<Attr = A>> {
+    /// Every character left alive is at peace with every other one. The winner is the side
+    /// they fight for, which for charmed characters is not their own team.
+    LastTeamStanding,
+    /// No member of the team is left alive
+    TeamEliminated(Team),
+    /// The given number of turns has been played
+    TurnLimit(usize),
+    /// The character is defeated, and its team with it
+    ProtectedCharacter(CharacterId),
+    Custom(EndCheck<A, S, M>),
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> EndCondition<A, S, M> {
+    /// `turn_over` is set when checking between turns, the only time a turn limit can be hit
+    pub(crate) fn evaluate(
+        &self,
+        encounter: &Encounter<A, S, M>,
+        turn_over: bool,
+    ) -> Option<Option<Team>> {
+        let survivors = encounter.survivors();
+        let team = |i: CharacterId| encounter.characters()[i].0.team();
+        let winner_against = |loser: Team| {
+            unique_team(
+                survivors
+                    .iter()
+                    .map(|i| team(*i))
+                    .filter(|t| encounter.factions().is_hostile(*t, loser)),
+            )
+        };
+        match self {
+            Self::LastTeamStanding => {
+                survivors
+                    .iter()
+                    .all(|a| survivors.iter().all(|b| !encounter.is_hostile(*a, *b)))
+                    .then(|| {
+                        unique_team(survivors.iter().filter_map(|i| {
+                            match encounter.allegiance(*i)? {
+                                Allegiance::Team(t) => Some(t),
+                                Allegiance::Hostile => None,
+                            }
+                        }))
+                    })
+            }
+            Self::TeamEliminated(t) => survivors
+                .iter()
+                .all(|i| team(*i) != *t)
+                .then(|| winner_against(*t)),
+            Self::TurnLimit(n) => (turn_over && encounter.turn() >= *n).then_some(None),
+            Self::ProtectedCharacter(i) => encounter
+                .is_defeated(*i)
+                .then(|| winner_against(encounter.characters().get(*i)?.0.team())),
+            Self::Custom(f) => f(encounter),
+        }
+    }
+}
+
+fn unique_team(mut teams: impl Iterator<Item = Team>) -> Option<Team> {
+    let first = teams.next()?;
+    teams.all(|t| t == first).then_some(first)
+}
+
+/// How an encounter ended
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EncounterOutcome {
+    winner: Option<Team>,
+    turns: usize,
+    survivors: Vec<CharacterId>,
+}
+
+impl EncounterOutcome {
+    pub(crate) fn new(winner: Option<Team>, turns: usize, survivors: Vec<CharacterId>) -> Self {
+        Self {
+            winner,
+            turns,
+            survivors,
+        }
+    }
+
+    /// The winning team, or `None` for a draw
+    pub fn winner(&self) -> Option<Team> {
+        self.winner
+    }
+
+    /// Number of turns started before the encounter ended
+    pub fn turns(&self) -> usize {
+        self.turns
+    }
+
+    /// Characters that were not defeated
+    pub fn survivors(&self) -> &[CharacterId] {
+        &self.survivors
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{ActionResult, Attribute, CharacterId, Delta, Status};
+
+/// Most combinations of random choices [`Encounter::preview`](crate::Encounter::preview) walks
+/// through before it falls back to sampling
+pub const MAX_PREVIEW_OUTCOMES: usize = 1024;
+
+/// Number of times an action with too many possible outcomes is sampled by
+/// [`Encounter::preview`](crate::Encounter::preview)
+pub const PREVIEW_SAMPLES: usize = 64;
+
+/// The predicted effect of an action, see [`Encounter::preview`](crate::Encounter::preview)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview<A: Attribute, S: Status> {
+    characters: Vec<CharacterPreview<A, S>>,
+    outcomes: usize,
+    exact: bool,
+}
+
+impl<A: Attribute, S: Status> Preview<A, S> {
+    /// Summarizes the possible results of an action along with their probabilities, which add
+    /// up to one. `exact` is set if they are every possible result rather than samples.
+    pub(crate) fn from_outcomes(outcomes: &[(ActionResult<A, S>, f64)], exact: bool) -> Self
+    where
+        A::Value: Into<f64>,
+    {
+        let mut characters: Vec<CharacterPreview<A, S>> = vec![];
+        let mut entry = |id: CharacterId| -> usize {
+            match characters.iter().position(|c| c.character == id) {
+                Some(i) => i,
+                None => {
+                    characters.push(CharacterPreview::new(id));
+                    characters.len() - 1
+                }
+            }
+        };
+        let mut attributes = vec![];
+        let mut statuses = vec![];
+        for (deltas, _) in outcomes {
+            for (id, delta) in deltas.deltas() {
+                let i = entry(*id);
+                match delta {
+                    Delta::Attribute(a) if !attributes.contains(&(i, a.identifier())) => {
+                        attributes.push((i, a.identifier()))
+                    }
+                    Delta::StatusAdded(s) | Delta::StatusRemoved(s)
+                        if !statuses.contains(&(i, s)) =>
+                    {
+                        statuses.push((i, s))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (i, identifier) in attributes {
+            let id = characters[i].character;
+            let totals: Vec<(A::Value, f64)> = outcomes
+                .iter()
+                .map(|(deltas, p)| {
+                    let total = deltas
+                        .deltas()
+                        .iter()
+                        .filter(|(c, _)| *c == id)
+                        .filter_map(|(_, d)| match d {
+                            Delta::Attribute(a) if a.identifier() == identifier => Some(a.delta()),
+                            _ => None,
+                        })
+                        .fold(A::Value::default(), |a, b| a + b);
+                    (total, *p)
+                })
+                .collect();
+            let pick = |better: fn(&A::Value, &A::Value) -> bool| {
+                totals
+                    .iter()
+                    .map(|(v, _)| *v)
+                    .reduce(|a, b| if better(&b, &a) { b } else { a })
+                    .unwrap_or_default()
+            };
+            characters[i].attributes.push(AttributePreview {
+                identifier: identifier.clone(),
+                min: pick(|a, b| a < b),
+                max: pick(|a, b| a > b),
+                expected: totals.iter().map(|(v, p)| (*v).into() * p).sum(),
+            });
+        }
+
+        for (i, status) in statuses {
+            let id = characters[i].character;
+            let mut added = 0.;
+            let mut removed = 0.;
+            for (deltas, p) in outcomes {
+                // Only the last change of the status in a sample counts
+                let last = deltas.deltas().iter().rev().find_map(|(c, d)| match d {
+                    Delta::StatusAdded(s) if *c == id && s == status => Some(true),
+                    Delta::StatusRemoved(s) if *c == id && s == status => Some(false),
+                    _ => None,
+                });
+                match last {
+                    Some(true) => added += p,
+                    Some(false) => removed += p,
+                    None => {}
+                }
+            }
+            for (chance, is_added) in [(added, true), (removed, false)] {
+                if chance > 0. {
+                    characters[i].statuses.push(StatusPreview {
+                        status: status.clone(),
+                        added: is_added,
+                        chance,
+                    });
+                }
+            }
+        }
+
+        Self {
+            characters,
+            outcomes: outcomes.len(),
+            exact,
+        }
+    }
+
+    /// Every affected character, in order of first appearance
+    pub fn characters(&self) -> &[CharacterPreview<A, S>] {
+        &self.characters
+    }
+
+    pub fn character(&self, id: CharacterId) -> Option<&CharacterPreview<A, S>> {
+        self.characters.iter().find(|c| c.character == id)
+    }
+
+    /// How many applications the preview is based on: every possible combination of random
+    /// choices if it is exact, [`PREVIEW_SAMPLES`] random samples otherwise
+    pub fn outcomes(&self) -> usize {
+        self.outcomes
+    }
+
+    /// Whether the preview covers every possible outcome, which makes the minimum, maximum and
+    /// expected values and the chances exact rather than estimates. Only actions with more than
+    /// [`MAX_PREVIEW_OUTCOMES`] possible outcomes are sampled.
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+}
+
+/// The predicted effect of an action on a single character
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterPreview<A: Attribute, S: Status> {
+    character: CharacterId,
+    attributes: Vec<AttributePreview<A>>,
+    statuses: Vec<StatusPreview<S>>,
+}
+
+impl<A: Attribute, S: Status> CharacterPreview<A, S> {
+    fn new(character: CharacterId) -> Self {
+        Self {
+            character,
+            attributes: vec![],
+            statuses: vec![],
+        }
+    }
+
+    pub fn character(&self) -> CharacterId {
+        self.character
+    }
+
+    pub fn attributes(&self) -> &[AttributePreview<A>] {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, identifier: &A::Identifier) -> Option<&AttributePreview<A>> {
+        self.attributes.iter().find(|a| &a.identifier == identifier)
+    }
+
+    pub fn statuses(&self) -> &[StatusPreview<S>] {
+        &self.statuses
+    }
+}
+
+/// The predicted net change of an attribute, after routing and clamping
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributePreview<A: Attribute> {
+    identifier: A::Identifier,
+    min: A::Value,
+    max: A::Value,
+    expected: f64,
+}
+
+impl<A: Attribute> AttributePreview<A> {
+    pub fn identifier(&self) -> &A::Identifier {
+        &self.identifier
+    }
+
+    pub fn min(&self) -> A::Value {
+        self.min
+    }
+
+    pub fn max(&self) -> A::Value {
+        self.max
+    }
+
+    pub fn expected(&self) -> f64 {
+        self.expected
+    }
+}
+
+/// A status the action may add or remove
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusPreview<S: Status> {
+    status: S,
+    added: bool,
+    chance: f64,
+}
+
+impl<S: Status> StatusPreview<S> {
+    pub fn status(&self) -> &S {
+        &self.status
+    }
+
+    /// Whether the status is added rather than removed
+    pub fn is_added(&self) -> bool {
+        self.added
+    }
+
+    /// Probability of the change, between `0` and `1`
+    pub fn chance(&self) -> f64 {
+        self.chance
+    }
+}
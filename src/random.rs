@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+/// A small deterministic pseudo random number generator (SplitMix64). Its whole state is a
+/// single `u64`, so it can be stored and replayed alongside the rest of an encounter and yields
+/// the same sequence on every platform.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Rng {
+    state: u64,
+    /// Set while enumerating every outcome, see [`Rng::enumerate`]
+    #[serde(skip)]
+    script: Option<Script>,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            script: None,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`, or `0` if `bound` is `0`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if self.script.is_some() {
+            return self.choose(&vec![1; bound as usize]).unwrap_or(0) as u64;
+        }
+        if bound == 0 {
+            return 0;
+        }
+        self.next_u64() % bound
+    }
+
+    /// An index into `weights`, picked with a probability proportional to its weight, or
+    /// `None` if no weight is positive
+    pub fn choose(&mut self, weights: &[u64]) -> Option<usize> {
+        if let Some(script) = &mut self.script {
+            return script.choose(weights);
+        }
+        let mut roll = self.below(weights.iter().sum());
+        weights.iter().position(|w| {
+            let hit = roll < *w;
+            roll = roll.saturating_sub(*w);
+            hit
+        })
+    }
+
+    /// A generator that instead of rolling walks through every combination of choices, one
+    /// combination per run: the first run takes the first option of every choice, and
+    /// [`Rng::advance`] moves on to the next combination
+    pub(crate) fn enumerate() -> Self {
+        Self {
+            state: 0,
+            script: Some(Script::default()),
+        }
+    }
+
+    /// Moves on to the next combination of choices, returning `false` once every combination
+    /// has been run
+    pub(crate) fn advance(&mut self) -> bool {
+        self.script.as_mut().is_some_and(Script::advance)
+    }
+
+    /// The probability of the combination of choices of the last run
+    pub(crate) fn probability(&self) -> f64 {
+        self.script.as_ref().map_or(1., Script::probability)
+    }
+}
+
+/// The choices made in the current run of an enumerating [`Rng`]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+struct Script {
+    choices: Vec<(usize, Vec<u64>)>,
+    next: usize,
+}
+
+impl Script {
+    fn choose(&mut self, weights: &[u64]) -> Option<usize> {
+        if self.next == self.choices.len() {
+            let first = weights.iter().position(|w| *w > 0)?;
+            self.choices.push((first, weights.to_vec()));
+        }
+        let (picked, _) = self.choices[self.next];
+        self.next += 1;
+        Some(picked)
+    }
+
+    fn advance(&mut self) -> bool {
+        self.next = 0;
+        while let Some((picked, weights)) = self.choices.last_mut() {
+            if let Some(i) = (*picked + 1..weights.len()).find(|i| weights[*i] > 0) {
+                *picked = i;
+                return true;
+            }
+            self.choices.pop();
+        }
+        false
+    }
+
+    fn probability(&self) -> f64 {
+        self.choices
+            .iter()
+            .map(|(picked, weights)| weights[*picked] as f64 / weights.iter().sum::<u64>() as f64)
+            .product()
+    }
+}
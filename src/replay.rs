@@ -0,0 +1,111 @@
+use crate::{ActionError, Attribute, CharacterId, EncounterState, Modifier, Status};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// An action executed through [`Encounter::execute`](crate::Encounter::execute), identified by
+/// the name of the action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Command {
+    actor: CharacterId,
+    action: String,
+    targets: Vec<CharacterId>,
+}
+
+impl Command {
+    pub fn new<T: ToString>(actor: CharacterId, action: T, targets: Vec<CharacterId>) -> Self {
+        Self {
+            actor,
+            action: action.to_string(),
+            targets,
+        }
+    }
+
+    pub fn actor(&self) -> CharacterId {
+        self.actor
+    }
+
+    pub fn action(&self) -> &str {
+        self.action.as_str()
+    }
+
+    pub fn targets(&self) -> &[CharacterId] {
+        &self.targets
+    }
+}
+
+/// A single step of an [`EncounterLog`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LogEntry {
+    Execute(Command),
+    NextTurn,
+    Undo,
+    Redo,
+    Despawn(CharacterId),
+    Seed(u64),
+    /// The state hash of the encounter at this point, see
+    /// [`Encounter::state_hash`](crate::Encounter::state_hash). It can only be verified by
+    /// builds with the toolchain that recorded it.
+    Checkpoint(u64),
+}
+
+/// Everything needed to reconstruct an encounter: a snapshot of the state it was in when
+/// recording started and every command since, in order. See
+/// [`Encounter::start_recording`](crate::Encounter::start_recording).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "EncounterState<A, S, M>: Serialize",
+    deserialize = "EncounterState<A, S, M>: Deserialize<'de>"
+))]
+pub struct EncounterLog<A: Attribute, S: Status, M: Modifier<Attr = A>> {
+    initial: EncounterState<A, S, M>,
+    entries: Vec<LogEntry>,
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> Clone for EncounterLog<A, S, M> {
+    fn clone(&self) -> Self {
+        Self {
+            initial: self.initial.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<A: Attribute, S: Status, M: Modifier<Attr = A>> EncounterLog<A, S, M> {
+    pub(crate) fn new(initial: EncounterState<A, S, M>) -> Self {
+        Self {
+            initial,
+            entries: vec![],
+        }
+    }
+
+    pub(crate) fn push(&mut self, entry: LogEntry) {
+        self.entries.push(entry);
+    }
+
+    /// The state of the encounter the log starts from
+    pub fn initial(&self) -> &EncounterState<A, S, M> {
+        &self.initial
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+}
+
+/// Why replaying an [`EncounterLog`] failed
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ReplayError<A: Attribute, S: Status> {
+    #[error("the state diverged from the log at entry {entry}")]
+    Desync {
+        entry: usize,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("entry {entry} uses the unknown action {action}")]
+    UnknownAction { entry: usize, action: String },
+    #[error("entry {entry} could not be executed: {error}")]
+    Action {
+        entry: usize,
+        error: ActionError<A, S>,
+    },
+}
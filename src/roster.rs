@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Index, IndexMut};
+
+/// A handle to a character in a [`Roster`]. Slots of removed characters are reused, but every
+/// reuse bumps the slot's generation, so an id kept around from before never refers to the
+/// character that took its place.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharacterId {
+    index: u32,
+    generation: u32,
+}
+
+impl CharacterId {
+    /// Position of the character in roster order
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Values keyed by [`CharacterId`], kept in insertion order. Looking up an id whose character
+/// has been removed yields `None`, and indexing with one panics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Roster<T> {
+    slots: Vec<Slot<T>>,
+}
+
+impl<T> Default for Roster<T> {
+    fn default() -> Self {
+        Self { slots: vec![] }
+    }
+}
+
+impl<T> Roster<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` to the first free slot
+    pub fn insert(&mut self, value: T) -> CharacterId {
+        let index = match self.slots.iter().position(|s| s.value.is_none()) {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+        let slot = &mut self.slots[index];
+        slot.value = Some(value);
+        CharacterId {
+            index: index as u32,
+            generation: slot.generation,
+        }
+    }
+
+    /// Removes the character, invalidating `id`
+    pub fn remove(&mut self, id: CharacterId) -> Option<T> {
+        let slot = self.slot_mut(id)?;
+        let value = slot.value.take();
+        slot.generation += 1;
+        value
+    }
+
+    pub fn contains(&self, id: CharacterId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: CharacterId) -> Option<&T> {
+        self.slots
+            .get(id.index())
+            .filter(|s| s.generation == id.generation)
+            .and_then(|s| s.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: CharacterId) -> Option<&mut T> {
+        self.slot_mut(id).and_then(|s| s.value.as_mut())
+    }
+
+    fn slot_mut(&mut self, id: CharacterId) -> Option<&mut Slot<T>> {
+        self.slots
+            .get_mut(id.index())
+            .filter(|s| s.generation == id.generation && s.value.is_some())
+    }
+
+    /// Number of characters in the roster
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|s| s.value.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CharacterId, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, s)| {
+            let id = CharacterId {
+                index: index as u32,
+                generation: s.generation,
+            };
+            s.value.as_ref().map(|v| (id, v))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (CharacterId, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, s)| {
+            let id = CharacterId {
+                index: index as u32,
+                generation: s.generation,
+            };
+            s.value.as_mut().map(|v| (id, v))
+        })
+    }
+
+    /// Ids of every character, in roster order
+    pub fn ids(&self) -> Vec<CharacterId> {
+        self.iter().map(|(id, _)| id).collect()
+    }
+
+    /// A roster with the same ids holding `f` of every value, e.g. to borrow parts of each
+    /// character
+    pub fn map<'a, U>(&'a self, mut f: impl FnMut(&'a T) -> U) -> Roster<U> {
+        Roster {
+            slots: self
+                .slots
+                .iter()
+                .map(|s| Slot {
+                    generation: s.generation,
+                    value: s.value.as_ref().map(&mut f),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn map_mut<'a, U>(&'a mut self, mut f: impl FnMut(&'a mut T) -> U) -> Roster<U> {
+        Roster {
+            slots: self
+                .slots
+                .iter_mut()
+                .map(|s| Slot {
+                    generation: s.generation,
+                    value: s.value.as_mut().map(&mut f),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<U, V> Roster<(U, V)> {
+    /// Splits a roster of pairs into two rosters with the same ids
+    pub fn unzip(self) -> (Roster<U>, Roster<V>) {
+        let mut left = Roster::new();
+        let mut right = Roster::new();
+        for Slot { generation, value } in self.slots {
+            let (u, v) = value.unzip();
+            left.slots.push(Slot {
+                generation,
+                value: u,
+            });
+            right.slots.push(Slot {
+                generation,
+                value: v,
+            });
+        }
+        (left, right)
+    }
+}
+
+impl<T> FromIterator<T> for Roster<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            slots: iter
+                .into_iter()
+                .map(|value| Slot {
+                    generation: 0,
+                    value: Some(value),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Roster<T> {
+    fn from(values: Vec<T>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T> Index<CharacterId> for Roster<T> {
+    type Output = T;
+
+    fn index(&self, id: CharacterId) -> &T {
+        self.get(id)
+            .unwrap_or_else(|| panic!("{id:?} is not in the roster"))
+    }
+}
+
+impl<T> IndexMut<CharacterId> for Roster<T> {
+    fn index_mut(&mut self, id: CharacterId) -> &mut T {
+        self.get_mut(id)
+            .unwrap_or_else(|| panic!("{id:?} is not in the roster"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_ids() {
+        let mut roster: Roster<&str> = vec!["a", "b"].into();
+        let ids = roster.ids();
+        assert_eq!(roster.remove(ids[0]), Some("a"));
+        assert_eq!(roster.get(ids[0]), None);
+
+        let c = roster.insert("c");
+        assert_eq!(c.index(), ids[0].index());
+        assert_ne!(c, ids[0]);
+        assert_eq!(roster.get(ids[0]), None);
+        assert_eq!(roster.remove(ids[0]), None);
+        assert_eq!(roster[c], "c");
+        assert_eq!(roster.ids(), vec![c, ids[1]]);
+
+        let (upper, len) = roster.map(|s| (s.to_uppercase(), s.len())).unzip();
+        assert_eq!(upper[c], "C");
+        assert_eq!(len.get(ids[0]), None);
+    }
+}
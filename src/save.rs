@@ -0,0 +1,407 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[cfg(feature = "binary")]
+use bincode::Options;
+
+/// Version of the save format of this crate. It is bumped whenever a serialized type changes in
+/// a way older saves cannot be read with, and a migration for the previous version is added
+/// to [`Migrations::new`].
+pub const SAVE_VERSION: u32 = 1;
+
+/// Upgrades a document of one version to the next, before it is deserialized
+pub type Migration = fn(&mut Value) -> Result<(), SaveError>;
+
+/// Why a save could not be written or read
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("the document is not a versioned save")]
+    NotVersioned,
+    #[error("the save has version {version}, but only versions up to {supported} are supported")]
+    TooNew { version: u32, supported: u32 },
+    #[error(
+        "the save has game version {version}, but only versions up to {supported} are supported"
+    )]
+    GameTooNew { version: u32, supported: u32 },
+    #[error("migrating from version {version} failed: {message}")]
+    Migration { version: u32, message: String },
+    #[cfg(feature = "binary")]
+    #[error(transparent)]
+    Binary(#[from] bincode::Error),
+    #[cfg(feature = "binary")]
+    #[error(
+        "the binary save has version {version} and game version {game_version}, but only \
+         version {supported} and game version {supported_game} can be read"
+    )]
+    BinaryVersion {
+        version: u32,
+        game_version: u32,
+        supported: u32,
+        supported_game: u32,
+    },
+}
+
+/// The envelope every save is written in. `version` is the [`SAVE_VERSION`] of the crate that
+/// wrote it and `game_version` the version of the game's own types, see
+/// [`Migrations::with_game_version`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Versioned<T> {
+    version: u32,
+    #[serde(default)]
+    game_version: u32,
+    data: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn game_version(&self) -> u32 {
+        self.game_version
+    }
+
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    pub fn into_data(self) -> T {
+        self.data
+    }
+}
+
+/// Writes saves with the current versions and upgrades older ones through a chain of
+/// [`Migration`]s before deserializing them. Each migration takes the `data` of a document from
+/// its version to the next, and versions without a migration are read as they are.
+///
+/// The crate and the game are versioned separately. The migrations of the crate upgrade saves
+/// to [`SAVE_VERSION`] first, then those registered by the game with
+/// [`Migrations::with_game_migration`] upgrade them to [`Migrations::with_game_version`], so
+/// neither has to know about the other's versions.
+#[derive(Debug, Clone)]
+pub struct Migrations {
+    version: u32,
+    steps: BTreeMap<u32, Vec<Migration>>,
+    game_version: u32,
+    game_steps: BTreeMap<u32, Vec<Migration>>,
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrations {
+    /// The migrations of the crate itself, for saves up to [`SAVE_VERSION`], and game
+    /// version 0
+    pub fn new() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            steps: BTreeMap::new(),
+            game_version: 0,
+            game_steps: BTreeMap::new(),
+        }
+    }
+
+    /// The game version new saves are written with
+    pub fn with_game_version(mut self, game_version: u32) -> Self {
+        self.game_version = game_version;
+        self
+    }
+
+    /// Adds a migration upgrading documents of game version `from` to `from + 1`. Migrations
+    /// for the same version run in the order they were added.
+    pub fn with_game_migration(mut self, from: u32, migration: Migration) -> Self {
+        self.game_steps.entry(from).or_default().push(migration);
+        self
+    }
+
+    /// The version of the crate new saves are written with, [`SAVE_VERSION`]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn game_version(&self) -> u32 {
+        self.game_version
+    }
+
+    /// Wraps `value` in an envelope with the current versions
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<String, SaveError> {
+        Ok(serde_json::to_string(&self.envelope(value))?)
+    }
+
+    fn envelope<T>(&self, data: T) -> Versioned<T> {
+        Versioned {
+            version: self.version,
+            game_version: self.game_version,
+            data,
+        }
+    }
+
+    /// Reads a save of any version up to the current one
+    pub fn load<T: DeserializeOwned>(&self, document: &str) -> Result<T, SaveError> {
+        let data = self.upgrade(serde_json::from_str(document)?)?;
+        Ok(serde_json::from_value(data)?)
+    }
+
+    /// Like [`Migrations::save`], but in the compact encoding of [`to_binary`]
+    #[cfg(feature = "binary")]
+    pub fn save_binary<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, SaveError> {
+        to_binary(&self.envelope(value))
+    }
+
+    /// Reads a save written by [`Migrations::save_binary`]. Binary saves carry no field names,
+    /// so migrations cannot run on them and only saves of the current version can be read.
+    /// Older ones have to be converted with the version of the game that wrote them, or kept as
+    /// JSON.
+    #[cfg(feature = "binary")]
+    pub fn load_binary<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, SaveError> {
+        let (version, game_version): (u32, u32) =
+            binary_options().allow_trailing_bytes().deserialize(bytes)?;
+        if (version, game_version) != (self.version, self.game_version) {
+            return Err(SaveError::BinaryVersion {
+                version,
+                game_version,
+                supported: self.version,
+                supported_game: self.game_version,
+            });
+        }
+        Ok(from_binary::<Versioned<T>>(bytes)?.data)
+    }
+
+    /// Takes the envelope of a save and returns its data upgraded to the current versions.
+    /// Saves without a game version are read as game version 0.
+    pub fn upgrade(&self, document: Value) -> Result<Value, SaveError> {
+        let Value::Object(mut envelope) = document else {
+            return Err(SaveError::NotVersioned);
+        };
+        let version = envelope
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(SaveError::NotVersioned)? as u32;
+        let game_version = match envelope.get("game_version") {
+            None => 0,
+            Some(v) => v.as_u64().ok_or(SaveError::NotVersioned)? as u32,
+        };
+        if version > self.version {
+            return Err(SaveError::TooNew {
+                version,
+                supported: self.version,
+            });
+        }
+        if game_version > self.game_version {
+            return Err(SaveError::GameTooNew {
+                version: game_version,
+                supported: self.game_version,
+            });
+        }
+        let mut data = envelope.remove("data").ok_or(SaveError::NotVersioned)?;
+        let crate_steps = self.steps.range(version..self.version);
+        let game_steps = self.game_steps.range(game_version..self.game_version);
+        for (_, migrations) in crate_steps.chain(game_steps) {
+            for migration in migrations {
+                migration(&mut data)?;
+            }
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "binary")]
+fn binary_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_little_endian()
+        .with_varint_encoding()
+        .reject_trailing_bytes()
+}
+
+/// Encodes `value` with `bincode`, with variable length little endian integers. It uses the same
+/// serde model as the JSON saves, at a fraction of the size, which makes it suitable for syncing
+/// state over the network or storing many replays. The output only depends on the value, as
+/// hash maps and sets are written in a fixed order, so it is the same on every platform. That
+/// order comes from [`stable_hash`](crate::stable_hash), so like state hashes it can change with
+/// the toolchain, although any order can be read back.
+#[cfg(feature = "binary")]
+pub fn to_binary<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SaveError> {
+    Ok(binary_options().serialize(value)?)
+}
+
+/// Decodes a value written by [`to_binary`]
+#[cfg(feature = "binary")]
+pub fn from_binary<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SaveError> {
+    Ok(binary_options().deserialize(bytes)?)
+}
+
+/// Renames every occurrence of the enum variant `from` to `to` in the values of fields called
+/// `field`, anywhere in `document`. Covers unit variants, written as strings, as well as
+/// variants with data, written as objects with a single key.
+///
+/// For example, renaming `AttributeChangeType::Mul` would be
+/// `rename_variant(document, "op", "Mul", "Multiply")`.
+pub fn rename_variant(document: &mut Value, field: &str, from: &str, to: &str) {
+    match document {
+        Value::Object(map) => {
+            if let Some(value) = map.get_mut(field) {
+                match value {
+                    Value::String(s) if s == from => *s = to.to_string(),
+                    Value::Object(variant) if variant.len() == 1 => {
+                        if let Some(inner) = variant.remove(from) {
+                            variant.insert(to.to_string(), inner);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            map.values_mut()
+                .for_each(|v| rename_variant(v, field, from, to));
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|v| rename_variant(v, field, from, to)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use crate::{
+        Action, AttributeChange, AttributeChangeType, Character, InnerAction, SimpleAction, Target,
+    };
+
+    #[test]
+    fn test_character_round_trip() {
+        let mut character = character(100.);
+        character.2.add(Buff::Wet);
+        character.3.add_by_change_tag("fire", Scale(0.5));
+        let migrations = Migrations::new();
+        let saved = migrations.save(&character).unwrap();
+        let loaded: Character<Attr, Buff, Scale> = migrations.load(&saved).unwrap();
+        assert_eq!(loaded.1.get_attribute_value(&Id::Mana), Some(50.));
+        assert!(loaded.2.contains(&Buff::Wet));
+        assert_eq!(loaded.3.state_hash(), character.3.state_hash());
+    }
+
+    #[test]
+    fn test_migrations() {
+        // Version 1 of a game called the variant `Multiply`, version 2 renamed it to `Mul`
+        let old = r#"{"version":1,"game_version":1,"data":{"name":"Halve","inner":{"Simple":{"target":"Target",
+            "elements":[{"AttributeChange":{"identifier":"Hp","change":0.5,"op":"Multiply"}}],
+            "follow_ups":[]}},"tags":[],"costs":[],"cooldown":null}}"#;
+        let migrations = Migrations::new().with_game_version(2);
+        assert!(migrations.load::<Action<Attr, Buff>>(old).is_err());
+
+        let migrations = migrations.with_game_migration(1, |document| {
+            rename_variant(document, "op", "Multiply", "Mul");
+            Ok(())
+        });
+        let action: Action<Attr, Buff> = migrations.load(old).unwrap();
+        let InnerAction::Simple(simple) = action.inner() else {
+            unreachable!()
+        };
+        let expected: SimpleAction<Attr, Buff> = SimpleAction::new(
+            Target::Target,
+            vec![AttributeChange::new(Id::Hp, 0.5)
+                .with_op(AttributeChangeType::Mul)
+                .into()],
+        );
+        assert_eq!(format!("{simple:?}"), format!("{expected:?}"));
+
+        let saved = migrations.save(&action).unwrap();
+        assert!(saved.starts_with(r#"{"version":1,"game_version":2,"#));
+        let newer = saved.replace("\"game_version\":2", "\"game_version\":3");
+        assert!(matches!(
+            migrations.load::<Action<Attr, Buff>>(&newer),
+            Err(SaveError::GameTooNew {
+                version: 3,
+                supported: 2
+            })
+        ));
+        let newer = saved.replace("\"version\":1", "\"version\":2");
+        assert!(matches!(
+            migrations.load::<Action<Attr, Buff>>(&newer),
+            Err(SaveError::TooNew {
+                version: 2,
+                supported: 1
+            })
+        ));
+
+        // Migrations of the crate run before those of the game
+        let mut migrations = Migrations {
+            version: 2,
+            ..Migrations::new()
+        }
+        .with_game_version(2)
+        .with_game_migration(1, |document| {
+            rename_variant(document, "op", "Mul", "Add");
+            Ok(())
+        });
+        migrations.steps.insert(
+            1,
+            vec![|document| {
+                rename_variant(document, "op", "Multiply", "Mul");
+                Ok(())
+            }],
+        );
+        let action: Action<Attr, Buff> = migrations.load(old).unwrap();
+        assert!(format!("{:?}", action.inner()).contains("op: Add"));
+    }
+
+    #[cfg(feature = "binary")]
+    #[test]
+    fn test_binary() {
+        use crate::{Encounter, EncounterLog};
+
+        let build = |tags: &[&str]| {
+            let mut character = character(100.);
+            for tag in tags {
+                character.2.add(Buff::Wet);
+                character.3.add_by_change_tag(*tag, Scale(0.5));
+            }
+            character
+        };
+        let character = build(&["fire", "ice", "wind"]);
+        let bytes = to_binary(&character).unwrap();
+        assert_eq!(bytes, to_binary(&build(&["wind", "ice", "fire"])).unwrap());
+        assert!(bytes.len() < serde_json::to_string(&character).unwrap().len() / 2);
+        let loaded: Character<Attr, Buff, Scale> = from_binary(&bytes).unwrap();
+        assert_eq!(loaded.3.state_hash(), character.3.state_hash());
+
+        let mut encounter = Encounter::new(vec![character_in(0, 100.), character_in(1, 100.)]);
+        let ids = encounter.characters().ids();
+        encounter.start_recording();
+        encounter.next_turn();
+        let hit = Action::new(
+            "Hit".to_string(),
+            InnerAction::Simple(SimpleAction::new(
+                Target::Target,
+                vec![AttributeChange::new(Id::Hp, -10.).into()],
+            )),
+        );
+        encounter.execute(ids[0], &hit, &[ids[1]]).unwrap();
+        let log = encounter.take_log().unwrap();
+        let migrations = Migrations::new();
+        let saved = migrations.save_binary(&log).unwrap();
+        let loaded: EncounterLog<Attr, Buff, Scale> = migrations.load_binary(&saved).unwrap();
+        assert_eq!(loaded.entries(), log.entries());
+        assert_eq!(loaded.initial().state_hash(), log.initial().state_hash());
+        assert!(matches!(
+            migrations
+                .with_game_version(2)
+                .load_binary::<EncounterLog<Attr, Buff, Scale>>(&saved),
+            Err(SaveError::BinaryVersion {
+                version: 1,
+                game_version: 0,
+                supported: 1,
+                supported_game: 2
+            })
+        ));
+    }
+}
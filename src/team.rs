@@ -0,0 +1,98 @@
+use crate::hash::stable_hash;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A side in an encounter. Characters start out in `Team(0)`.
+#[derive(
+    Default, Debug, Copy, Clone, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq, Hash,
+)]
+pub struct Team(pub u32);
+
+/// Which side a character currently fights for
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Allegiance {
+    Team(Team),
+    /// Hostile to every other character, e.g. while confused
+    Hostile,
+}
+
+/// Hostility relations between teams. Members of the same team are never hostile to each other,
+/// different teams are hostile unless declared otherwise.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Factions {
+    #[serde(with = "relations")]
+    relations: BTreeMap<(Team, Team), bool>,
+}
+
+impl Factions {
+    pub fn set_relation(&mut self, a: Team, b: Team, hostile: bool) {
+        self.relations.insert(Self::key(a, b), hostile);
+    }
+
+    pub fn is_hostile(&self, a: Team, b: Team) -> bool {
+        a != b
+            && self
+                .relations
+                .get(&Self::key(a, b))
+                .copied()
+                .unwrap_or(true)
+    }
+
+    pub fn are_hostile(&self, a: Allegiance, b: Allegiance) -> bool {
+        match (a, b) {
+            (Allegiance::Team(a), Allegiance::Team(b)) => self.is_hostile(a, b),
+            _ => true,
+        }
+    }
+
+    /// A hash of every relation
+    pub fn state_hash(&self) -> u64 {
+        stable_hash(&self.relations)
+    }
+
+    fn key(a: Team, b: Team) -> (Team, Team) {
+        (a.min(b), a.max(b))
+    }
+}
+
+/// Writes the relations as a list of `(a, b, hostile)` entries, since formats like JSON only
+/// allow strings as map keys
+mod relations {
+    use super::Team;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(
+        relations: &BTreeMap<(Team, Team), bool>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(relations.iter().map(|(&(a, b), &hostile)| (a, b, hostile)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<(Team, Team), bool>, D::Error> {
+        let entries = Vec::<(Team, Team, bool)>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|(a, b, hostile)| ((a, b), hostile))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factions_round_trip() {
+        let mut factions = Factions::default();
+        factions.set_relation(Team(2), Team(1), false);
+        factions.set_relation(Team(0), Team(3), true);
+        let json = serde_json::to_string(&factions).unwrap();
+        let loaded: Factions = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, factions);
+        assert!(!loaded.is_hostile(Team(1), Team(2)));
+        assert!(loaded.is_hostile(Team(3), Team(0)));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{Attribute, AttributeIdentifier, Character, CharacterBase, Modifier, Status, Team};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(
+    Debug, Default, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum Id {
+    #[default]
+    Hp,
+    MaxHp,
+    Mana,
+    Barrier,
+}
+
+impl AttributeIdentifier for Id {}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attr(pub f64);
+
+impl Attribute for Attr {
+    type Value = f64;
+    type Identifier = Id;
+
+    fn set_value(&mut self, new_value: Self::Value) {
+        self.0 = new_value.max(0.);
+    }
+
+    fn value(&self) -> Self::Value {
+        self.0
+    }
+}
+
+#[derive(
+    Debug, Default, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum Buff {
+    #[default]
+    Wet,
+    Frozen,
+    Stunned,
+    Charmed,
+    Confused,
+}
+
+impl Status for Buff {
+    fn blocks(&self, tags: &BTreeSet<String>) -> bool {
+        match self {
+            Buff::Stunned => true,
+            Buff::Frozen => tags.contains("spell"),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scale(pub f64);
+
+impl Modifier for Scale {
+    type Attr = Attr;
+
+    fn apply(&self, on: f64) -> f64 {
+        on * self.0
+    }
+}
+
+pub fn character_in(team: u32, hp: f64) -> Character<Attr, Buff, Scale> {
+    let mut character = character(hp);
+    character.0.set_team(Team(team));
+    character
+}
+
+pub fn character(hp: f64) -> Character<Attr, Buff, Scale> {
+    let mut character = CharacterBase::new("test");
+    character.1.add_attribute(Id::Hp, Attr(hp));
+    character.1.add_attribute(Id::MaxHp, Attr(hp));
+    character.1.add_attribute(Id::Mana, Attr(50.));
+    character.1.add_attribute(Id::Barrier, Attr(0.));
+    character
+}